@@ -0,0 +1,161 @@
+use contracts::*;
+
+use crate::ray::Ray;
+use crate::vec3::{Pnt3, Vec3};
+
+/// An axis aligned bounding box.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Aabb {
+    pub min: Pnt3,
+    pub max: Pnt3,
+}
+
+impl Aabb {
+    #[requires(min.x <= max.x && min.y <= max.y && min.z <= max.z)]
+    pub fn new(min: Pnt3, max: Pnt3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    /// The smallest box containing all `points`.
+    pub fn from_points(points: &[Pnt3]) -> Aabb {
+        points
+            .iter()
+            .fold(Aabb::empty(), |aabb, pnt| aabb.union_point(*pnt))
+    }
+
+    /// A box containing nothing, the identity of [`Aabb::union`].
+    pub fn empty() -> Aabb {
+        Aabb {
+            min: Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    /// A box containing everything, for unbounded primitives like planes.
+    pub fn infinite() -> Aabb {
+        Aabb {
+            min: Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            max: Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        (0..3).all(|i| self.min[i].is_finite() && self.max[i].is_finite())
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Vec3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: Vec3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    pub fn union_point(&self, pnt: Pnt3) -> Aabb {
+        self.union(&Aabb { min: pnt, max: pnt })
+    }
+
+    pub fn centroid(&self) -> Pnt3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn extent(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// Index of the axis with the largest extent.
+    pub fn longest_axis(&self) -> usize {
+        let extent = self.extent();
+        if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Surface area of the box, 0.0 for empty boxes.
+    pub fn surface_area(&self) -> f64 {
+        let extent = self.extent();
+        if extent.x < 0.0 || extent.y < 0.0 || extent.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+    }
+
+    /// Slab test against the ray, with `inv_dir` the component-wise inverse of the ray direction.
+    /// Returns the distance at which the ray enters the box, if it does so before `t_max`.
+    pub fn hit(&self, ray: &Ray, inv_dir: &Vec3, t_max: f64) -> Option<f64> {
+        let mut t_enter = 0.0f64;
+        let mut t_exit = t_max;
+        for i in 0..3 {
+            let mut t_0 = (self.min[i] - ray.origin[i]) * inv_dir[i];
+            let mut t_1 = (self.max[i] - ray.origin[i]) * inv_dir[i];
+            if inv_dir[i] < 0.0 {
+                std::mem::swap(&mut t_0, &mut t_1);
+            }
+            // NaN (ray origin on a slab of a zero direction component) is ignored by min/max
+            t_enter = t_enter.max(t_0);
+            t_exit = t_exit.min(t_1);
+            if t_exit < t_enter {
+                return None;
+            }
+        }
+        Some(t_enter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::UnitVec3;
+
+    #[test]
+    fn union_and_area() {
+        let a = Aabb::new(Pnt3::new(0.0, 0.0, 0.0), Pnt3::new(1.0, 1.0, 1.0));
+        let b = Aabb::new(Pnt3::new(-1.0, 0.5, 0.0), Pnt3::new(0.0, 2.0, 1.0));
+        let union = a.union(&b);
+        assert_eq!(union.min, Pnt3::new(-1.0, 0.0, 0.0));
+        assert_eq!(union.max, Pnt3::new(1.0, 2.0, 1.0));
+        assert_eq!(union.longest_axis(), 0);
+        assert_eq!(a.surface_area(), 6.0);
+        assert_eq!(Aabb::empty().surface_area(), 0.0);
+        assert_eq!(Aabb::empty().union(&a), a);
+        assert!(a.is_finite());
+        assert!(!Aabb::infinite().is_finite());
+    }
+
+    #[test]
+    fn hit() {
+        let aabb = Aabb::new(Pnt3::new(0.0, 0.0, 0.0), Pnt3::new(1.0, 1.0, 1.0));
+        let inv = |ray: &Ray| Vec3::new(1.0 / ray.dir.x, 1.0 / ray.dir.y, 1.0 / ray.dir.z);
+
+        let ray = Ray::new(Pnt3::new(0.5, 0.5, -1.0), UnitVec3::new(0.0, 0.0, 1.0));
+        assert_eq!(aabb.hit(&ray, &inv(&ray), f64::INFINITY), Some(1.0));
+        assert_eq!(aabb.hit(&ray, &inv(&ray), 0.5), None);
+
+        // Starting inside
+        let ray = Ray::new(Pnt3::new(0.5, 0.5, 0.5), UnitVec3::new(1.0, 1.0, 0.0));
+        assert_eq!(aabb.hit(&ray, &inv(&ray), f64::INFINITY), Some(0.0));
+
+        // Behind the ray
+        let ray = Ray::new(Pnt3::new(0.5, 0.5, 2.0), UnitVec3::new(0.0, 0.0, 1.0));
+        assert_eq!(aabb.hit(&ray, &inv(&ray), f64::INFINITY), None);
+
+        // Grazing a face
+        let ray = Ray::new(Pnt3::new(0.0, 0.5, -1.0), UnitVec3::new(0.0, 0.0, 1.0));
+        assert_eq!(aabb.hit(&ray, &inv(&ray), f64::INFINITY), Some(1.0));
+
+        // Missing
+        let ray = Ray::new(Pnt3::new(2.0, 0.5, -1.0), UnitVec3::new(0.0, 0.0, 1.0));
+        assert_eq!(aabb.hit(&ray, &inv(&ray), f64::INFINITY), None);
+    }
+}
@@ -0,0 +1,322 @@
+use contracts::*;
+
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Number of centroid bins evaluated per split.
+const BINS: usize = 12;
+/// Leaves are never split further below this size.
+const MIN_SPLIT_SIZE: usize = 2;
+/// Leaves are always split above this size, even if the SAH disagrees.
+const MAX_LEAF_SIZE: usize = 8;
+/// Cost of traversing a node relative to intersecting a primitive.
+const TRAVERSAL_COST: f64 = 1.0;
+
+/// A bounding volume hierarchy over primitives given by their bounding boxes,
+/// built with the surface area heuristic (SAH).
+/// https://pbr-book.org/3ed-2018/Primitives_and_Intersection_Acceleration/Bounding_Volume_Hierarchies
+///
+/// The hierarchy only stores primitive indices. Intersecting the primitives
+/// themselves is left to the caller, see [`Bvh::closest_hit`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    indices: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Node {
+    bounds: Aabb,
+    /// For leaves the first entry in `indices`, for inner nodes the index of the second child.
+    /// The first child of an inner node always directly follows it.
+    first: usize,
+    /// Number of primitives of a leaf, 0 for inner nodes.
+    count: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+    bounds: Aabb,
+    count: usize,
+}
+
+impl Bvh {
+    /// Builds the hierarchy over primitives with the given bounding boxes.
+    /// The primitive indices passed to [`Bvh::closest_hit`] index into `bounds`.
+    #[requires(bounds.iter().all(|aabb| aabb.is_finite()))]
+    pub fn build(bounds: &[Aabb]) -> Bvh {
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(2 * bounds.len()),
+            indices: (0..bounds.len()).collect(),
+        };
+        if !bounds.is_empty() {
+            let centroids = bounds.iter().map(Aabb::centroid).collect::<Vec<Vec3>>();
+            bvh.build_node(bounds, &centroids, 0, bounds.len());
+        }
+        bvh
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Bounds of all primitives in the hierarchy.
+    pub fn bounds(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::empty(), |node| node.bounds)
+    }
+
+    fn build_node(&mut self, bounds: &[Aabb], centroids: &[Vec3], start: usize, end: usize) {
+        let node_index = self.nodes.len();
+        let node_bounds = self.indices[start..end]
+            .iter()
+            .fold(Aabb::empty(), |aabb, i| aabb.union(&bounds[*i]));
+        self.nodes.push(Node {
+            bounds: node_bounds,
+            first: start,
+            count: end - start,
+        });
+
+        let count = end - start;
+        if count <= MIN_SPLIT_SIZE {
+            return;
+        }
+        let mid = match self.split(bounds, centroids, &node_bounds, start, end) {
+            Some(mid) => mid,
+            None => return,
+        };
+
+        self.build_node(bounds, centroids, start, mid);
+        let second = self.nodes.len();
+        self.build_node(bounds, centroids, mid, end);
+        self.nodes[node_index].first = second;
+        self.nodes[node_index].count = 0;
+    }
+
+    /// Partitions `indices[start..end]` along the cheapest SAH split.
+    /// Returns the partition point, or `None` if a leaf is cheaper.
+    fn split(
+        &mut self,
+        bounds: &[Aabb],
+        centroids: &[Vec3],
+        node_bounds: &Aabb,
+        start: usize,
+        end: usize,
+    ) -> Option<usize> {
+        let count = end - start;
+        let centroid_bounds = self.indices[start..end]
+            .iter()
+            .fold(Aabb::empty(), |aabb, i| aabb.union_point(centroids[*i]));
+        let axis = centroid_bounds.longest_axis();
+        let axis_min = centroid_bounds.min[axis];
+        let axis_extent = centroid_bounds.max[axis] - axis_min;
+        if axis_extent <= 0.0 {
+            // All centroids coincide, no split can separate them
+            return None;
+        }
+        let bin_of = |i: usize| {
+            let offset = (centroids[i][axis] - axis_min) / axis_extent;
+            ((offset * BINS as f64) as usize).min(BINS - 1)
+        };
+
+        let mut bins = [Bin {
+            bounds: Aabb::empty(),
+            count: 0,
+        }; BINS];
+        for i in &self.indices[start..end] {
+            let bin = &mut bins[bin_of(*i)];
+            bin.bounds = bin.bounds.union(&bounds[*i]);
+            bin.count += 1;
+        }
+
+        // Cost of splitting after each bin, sweeping from both sides
+        let mut costs = [0.0; BINS - 1];
+        let mut left = Bin {
+            bounds: Aabb::empty(),
+            count: 0,
+        };
+        for (split, bin) in bins[..BINS - 1].iter().enumerate() {
+            left.bounds = left.bounds.union(&bin.bounds);
+            left.count += bin.count;
+            costs[split] = left.count as f64 * left.bounds.surface_area();
+        }
+        let mut right = Bin {
+            bounds: Aabb::empty(),
+            count: 0,
+        };
+        for (split, bin) in bins[1..].iter().enumerate().rev() {
+            right.bounds = right.bounds.union(&bin.bounds);
+            right.count += bin.count;
+            costs[split] += right.count as f64 * right.bounds.surface_area();
+        }
+
+        let (best_split, best_cost) = costs
+            .iter()
+            .enumerate()
+            .min_by(|x, y| x.1.partial_cmp(y.1).unwrap())
+            .map(|(split, cost)| {
+                (
+                    split,
+                    TRAVERSAL_COST + cost / node_bounds.surface_area().max(f64::EPSILON),
+                )
+            })
+            .unwrap();
+        let leaf_cost = count as f64;
+        if count <= MAX_LEAF_SIZE && best_cost >= leaf_cost {
+            return None;
+        }
+
+        let mut mid = start;
+        for i in start..end {
+            if bin_of(self.indices[i]) <= best_split {
+                self.indices.swap(i, mid);
+                mid += 1;
+            }
+        }
+        if mid == start || mid == end {
+            return None;
+        }
+        Some(mid)
+    }
+
+    /// Finds the closest hit along the ray.
+    /// `intersect` is called with the index of every primitive whose bounds the ray
+    /// passes through and returns the distance along the ray and the hit itself.
+    pub fn closest_hit<T>(
+        &self,
+        ray: &Ray,
+        mut intersect: impl FnMut(usize) -> Option<(f64, T)>,
+    ) -> Option<(f64, T)> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let inv_dir = Vec3::new(1.0 / ray.dir.x, 1.0 / ray.dir.y, 1.0 / ray.dir.z);
+        let mut closest: Option<(f64, T)> = None;
+        let mut t_max = f64::INFINITY;
+        // Nodes to visit with the distance at which the ray enters them
+        let mut stack = Vec::with_capacity(64);
+        if let Some(t_enter) = self.nodes[0].bounds.hit(ray, &inv_dir, t_max) {
+            stack.push((0, t_enter));
+        }
+
+        while let Some((node_index, t_enter)) = stack.pop() {
+            if t_enter > t_max {
+                continue;
+            }
+            let node = &self.nodes[node_index];
+            if node.count > 0 {
+                for i in &self.indices[node.first..node.first + node.count] {
+                    if let Some((t, hit)) = intersect(*i) {
+                        if t < t_max {
+                            t_max = t;
+                            closest = Some((t, hit));
+                        }
+                    }
+                }
+                continue;
+            }
+
+            // Visit the nearer child first so the farther one can be culled by `t_max`
+            let first = node_index + 1;
+            let second = node.first;
+            let t_first = self.nodes[first].bounds.hit(ray, &inv_dir, t_max);
+            let t_second = self.nodes[second].bounds.hit(ray, &inv_dir, t_max);
+            match (t_first, t_second) {
+                (Some(t_first), Some(t_second)) => {
+                    if t_first <= t_second {
+                        stack.push((second, t_second));
+                        stack.push((first, t_first));
+                    } else {
+                        stack.push((first, t_first));
+                        stack.push((second, t_second));
+                    }
+                }
+                (Some(t_first), None) => stack.push((first, t_first)),
+                (None, Some(t_second)) => stack.push((second, t_second)),
+                (None, None) => {}
+            }
+        }
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::{Pnt3, UnitVec3};
+
+    /// Intersects a ray with a box, returning the entry distance.
+    fn hit_box(aabb: &Aabb, ray: &Ray) -> Option<f64> {
+        let inv_dir = Vec3::new(1.0 / ray.dir.x, 1.0 / ray.dir.y, 1.0 / ray.dir.z);
+        aabb.hit(ray, &inv_dir, f64::INFINITY)
+    }
+
+    fn random_boxes(count: usize) -> Vec<Aabb> {
+        let mut rand = random::default(42);
+        (0..count)
+            .map(|_| {
+                let min = Pnt3::new(
+                    random::Source::read_f64(&mut rand) * 100.0,
+                    random::Source::read_f64(&mut rand) * 100.0,
+                    random::Source::read_f64(&mut rand) * 100.0,
+                );
+                let size = random::Source::read_f64(&mut rand) * 5.0 + 0.1;
+                Aabb::new(min, min + Vec3::new(size, size, size))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty() {
+        let bvh = Bvh::build(&[]);
+        assert!(bvh.is_empty());
+        let ray = Ray::new(Pnt3::new(0.0, 0.0, 0.0), UnitVec3::new(0.0, 0.0, 1.0));
+        assert!(bvh.closest_hit(&ray, |_| Some((1.0, ()))).is_none());
+    }
+
+    #[test]
+    fn bounds_contain_everything() {
+        let boxes = random_boxes(100);
+        let bvh = Bvh::build(&boxes);
+        let bounds = boxes.iter().fold(Aabb::empty(), |aabb, b| aabb.union(b));
+        assert_eq!(bvh.bounds(), bounds);
+
+        let mut indices = bvh.indices.clone();
+        indices.sort();
+        assert_eq!(indices, (0..100).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn matches_linear_scan() {
+        let boxes = random_boxes(1000);
+        let bvh = Bvh::build(&boxes);
+        let mut rand = random::default(7);
+        for _ in 0..200 {
+            let origin = Pnt3::new(
+                random::Source::read_f64(&mut rand) * 100.0,
+                random::Source::read_f64(&mut rand) * 100.0,
+                -10.0,
+            );
+            let ray = Ray::new(
+                origin,
+                UnitVec3::new(
+                    random::Source::read_f64(&mut rand) - 0.5,
+                    random::Source::read_f64(&mut rand) - 0.5,
+                    1.0,
+                ),
+            );
+            let expected = boxes
+                .iter()
+                .enumerate()
+                .filter_map(|(i, aabb)| hit_box(aabb, &ray).map(|t| (t, i)))
+                .min_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+            let mut tested = 0;
+            let actual = bvh.closest_hit(&ray, |i| {
+                tested += 1;
+                hit_box(&boxes[i], &ray).map(|t| (t, i))
+            });
+            assert_eq!(actual, expected);
+            assert!(tested < boxes.len());
+        }
+    }
+}
@@ -1,16 +1,150 @@
 use contracts::*;
 
+use std::f64::consts::{FRAC_PI_2, PI};
+
 use crate::interval::Interval;
 use crate::ray::{Ray, UpRightBoundedRay};
-use crate::vec3::{Pnt3, UnitVec3};
+use crate::sampling;
+use crate::vec3::{Pnt3, UnitVec3, Vec3};
+
+/// How the camera maps points on its film to rays.
+/// Film coordinates run from `-aspect_ratio` on the left to `aspect_ratio` on the right
+/// and from `-1.0` at the bottom to `1.0` at the top.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// A pinhole camera, all rays start at the center.
+    /// `vfov` is the vertical field of view in radians.
+    Perspective { vfov: f64 },
+    /// Parallel rays starting on a view `height` units tall.
+    Orthographic { height: f64 },
+    /// Latitude and longitude of the full sphere around the center, for environment maps.
+    /// The film covers 90° per unit, a 2:1 image spans all 360°.
+    /// https://en.wikipedia.org/wiki/Equirectangular_projection
+    Equirectangular,
+    /// A fisheye lens covering the field of view `fov` in radians across the film's height,
+    /// up to 360°. The mapping continues beyond the image circle.
+    Fisheye { fov: f64, mapping: FisheyeMapping },
+    /// The six faces of a cube around the center with 90° each, laid out in a 3:2 image.
+    /// Top row left, front and right, bottom row back, up and down.
+    Cubemap,
+    /// Omni-directional stereo for VR, equirectangular panoramas for both eyes
+    /// `ipd` (interpupillary distance) apart, the left eye on top of the right in a 1:1 image.
+    /// https://developers.google.com/static/vr/jump/rendering-ods-content.pdf
+    OmniStereo { ipd: f64 },
+}
+
+/// How a fisheye lens maps the angle off the view direction to the distance from the center.
+/// https://en.wikipedia.org/wiki/Fisheye_lens#Mapping_function
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum FisheyeMapping {
+    /// Distance proportional to the angle.
+    #[default]
+    Equidistant,
+    /// Preserves relative areas.
+    Equisolid,
+}
+
+impl Projection {
+    /// A pinhole perspective projection with the vertical field of view in degrees.
+    #[requires(vfov > 0.0 && vfov < 180.0)]
+    pub fn perspective(vfov: f64) -> Projection {
+        Projection::Perspective {
+            vfov: vfov.to_radians(),
+        }
+    }
+
+    /// A fisheye projection with the field of view in degrees.
+    #[requires(fov > 0.0 && fov <= 360.0)]
+    pub fn fisheye(fov: f64, mapping: FisheyeMapping) -> Projection {
+        Projection::Fisheye {
+            fov: fov.to_radians(),
+            mapping,
+        }
+    }
+
+    /// The aspect ratio of images covering exactly the whole projection,
+    /// `None` if any aspect ratio works.
+    pub fn aspect_ratio(&self) -> Option<f64> {
+        match self {
+            Projection::Equirectangular => Some(2.0),
+            Projection::Cubemap => Some(1.5),
+            Projection::OmniStereo { .. } => Some(1.0),
+            _ => None,
+        }
+    }
+
+    /// Whether the projection has a single view direction, only those are focused by a lens.
+    pub fn is_planar(&self) -> bool {
+        matches!(
+            self,
+            Projection::Perspective { .. } | Projection::Orthographic { .. }
+        )
+    }
+}
+
+/// The unit direction at a `longitude` left to right and `latitude` bottom to top,
+/// in camera space with the view direction along z.
+fn spherical(longitude: f64, latitude: f64) -> Vec3 {
+    Vec3::new(
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+        latitude.cos() * longitude.cos(),
+    )
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection::perspective(45.0)
+    }
+}
+
+/// The shape of the lens opening, which shapes the out of focus highlights (bokeh).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Aperture {
+    #[default]
+    Circle,
+    /// A regular polygon formed by the aperture blades, rotated by `rotation` radians.
+    Polygon { blades: u32, rotation: f64 },
+}
+
+impl Aperture {
+    /// Samples a point uniformly distributed over the opening of radius 1.0.
+    pub fn sample(&self, u: (f64, f64)) -> (f64, f64) {
+        match *self {
+            Aperture::Circle => sampling::concentric_disk(u),
+            Aperture::Polygon { blades, rotation } => {
+                let (x, y) = sampling::uniform_polygon(blades, u);
+                let (sin, cos) = rotation.sin_cos();
+                (x * cos - y * sin, x * sin + y * cos)
+            }
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Camera {
+    /// Distance to the target the camera looks at.
     pub focal_length: f64,
 
     pub center: Pnt3,
     pub up: UnitVec3,
     pub right: UnitVec3,
+
+    pub projection: Projection,
+    /// Width over height of the view, `None` for the aspect ratio of the projection
+    /// if it has one, otherwise the image's.
+    pub aspect_ratio: Option<f64>,
+
+    /// Radius of the lens, 0.0 for a pinhole keeping everything in focus.
+    pub aperture_radius: f64,
+    pub aperture: Aperture,
+    /// Distance along the view direction of the plane in focus.
+    pub focus_distance: f64,
+
+    /// Times the shutter opens and closes, rays are spread evenly in between.
+    /// Moving objects blur over the part of their [`crate::scene::moving::Moving::interval`]
+    /// within it, which has to be set for shutters outside of 0.0 to 1.0.
+    pub shutter: Interval<f64>,
 }
 
 // #[invariant(self.up.cross(self.right) == Vec3::)]
@@ -41,12 +175,15 @@ impl Camera {
 
         let yaxis = xaxis.cross(zaxis).normalize().unwrap();
 
-        Camera {
-            focal_length: (target - origin).len(),
-            center: origin,
-            up: yaxis,
-            right: xaxis,
-        }
+        Camera::new((target - origin).len(), origin, yaxis, xaxis)
+    }
+
+    /// Creates a camera looking at the target from the origin through a lens of radius
+    /// `aperture_radius`, focused on the target.
+    #[requires(origin != target)]
+    #[requires(aperture_radius >= 0.0)]
+    pub fn look_at_focused(origin: Pnt3, target: Pnt3, aperture_radius: f64) -> Camera {
+        Camera::look_at(origin, target).with_aperture_radius(aperture_radius)
     }
 
     pub fn new(focal_length: f64, center: Pnt3, up: UnitVec3, right: UnitVec3) -> Camera {
@@ -55,29 +192,193 @@ impl Camera {
             center,
             up,
             right,
+            projection: Projection::default(),
+            aspect_ratio: None,
+            aperture_radius: 0.0,
+            aperture: Aperture::default(),
+            focus_distance: focal_length,
+            shutter: Interval::new(0.0, 0.0),
+        }
+    }
+
+    /// Returns the camera with another projection.
+    pub fn with_projection(mut self, projection: Projection) -> Camera {
+        self.projection = projection;
+        self
+    }
+
+    /// Returns the camera with a fixed aspect ratio, stretching the view to the image.
+    #[requires(aspect_ratio > 0.0)]
+    pub fn with_aspect_ratio(mut self, aspect_ratio: f64) -> Camera {
+        self.aspect_ratio = Some(aspect_ratio);
+        self
+    }
+
+    /// Returns the camera with a lens of radius `aperture_radius`, blurring everything
+    /// away from the focus distance.
+    #[requires(aperture_radius >= 0.0)]
+    pub fn with_aperture_radius(mut self, aperture_radius: f64) -> Camera {
+        self.aperture_radius = aperture_radius;
+        self
+    }
+
+    /// Returns the camera with another aperture shape.
+    pub fn with_aperture(mut self, aperture: Aperture) -> Camera {
+        self.aperture = aperture;
+        self
+    }
+
+    /// Returns the camera focused at `focus_distance` along the view direction.
+    #[requires(focus_distance > 0.0)]
+    pub fn with_focus_distance(mut self, focus_distance: f64) -> Camera {
+        self.focus_distance = focus_distance;
+        self
+    }
+
+    /// Returns the camera exposing from `open` to `close`.
+    #[requires(open <= close)]
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Camera {
+        self.shutter = Interval::new(open, close);
+        self
+    }
+
+    /// The direction the camera looks in.
+    pub fn forward(&self) -> UnitVec3 {
+        self.up.cross(self.right).normalize().unwrap()
+    }
+
+    /// The ray through the point `(x, y)` on the film, see [`Projection`].
+    pub fn ray(&self, x: f64, y: f64) -> Ray {
+        match self.projection {
+            Projection::Perspective { vfov } => {
+                let scale = (vfov / 2.0).tan();
+                let dir =
+                    Vec3::from(self.forward()) + self.right * (x * scale) + self.up * (y * scale);
+                Ray::new(self.center, dir.normalize().unwrap())
+            }
+            Projection::Orthographic { height } => {
+                let offset = self.right * (x * height / 2.0) + self.up * (y * height / 2.0);
+                Ray::new(self.center + offset, self.forward())
+            }
+            Projection::Equirectangular => {
+                let dir = spherical(x * FRAC_PI_2, y.clamp(-1.0, 1.0) * FRAC_PI_2);
+                Ray::new(self.center, self.to_world(dir).normalize().unwrap())
+            }
+            Projection::Fisheye { fov, mapping } => {
+                let r = x.hypot(y);
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * fov / 2.0,
+                    FisheyeMapping::Equisolid => 2.0 * (r * (fov / 4.0).sin()).min(1.0).asin(),
+                };
+                let phi = y.atan2(x);
+                let theta = theta.min(PI);
+                let dir = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+                Ray::new(self.center, self.to_world(dir).normalize().unwrap())
+            }
+            Projection::Cubemap => {
+                // Face coordinates from -1.0 to 1.0 within each square of the layout
+                let column = (x + 1.5).floor().clamp(0.0, 2.0);
+                let s = 2.0 * (x + 1.5 - column) - 1.0;
+                let top = y >= 0.0;
+                let t = if top { 2.0 * y - 1.0 } else { 2.0 * y + 1.0 };
+                let dir = match (top, column as u32) {
+                    (true, 0) => Vec3::new(-1.0, t, s),
+                    (true, 1) => Vec3::new(s, t, 1.0),
+                    (true, _) => Vec3::new(1.0, t, -s),
+                    (false, 0) => Vec3::new(-s, t, -1.0),
+                    (false, 1) => Vec3::new(s, 1.0, -t),
+                    (false, _) => Vec3::new(s, -1.0, t),
+                };
+                Ray::new(self.center, self.to_world(dir).normalize().unwrap())
+            }
+            Projection::OmniStereo { ipd } => {
+                let left_eye = y >= 0.0;
+                let y = if left_eye {
+                    2.0 * y - 1.0
+                } else {
+                    2.0 * y + 1.0
+                };
+                let longitude = x * PI;
+                let dir = spherical(longitude, y.clamp(-1.0, 1.0) * FRAC_PI_2);
+                // The eyes sit on a circle, each offset sideways from the view direction
+                let side = Vec3::new(longitude.cos(), 0.0, -longitude.sin());
+                let eye = if left_eye { -ipd / 2.0 } else { ipd / 2.0 };
+                Ray::new(
+                    self.center + self.to_world(side * eye),
+                    self.to_world(dir).normalize().unwrap(),
+                )
+            }
         }
     }
 
+    /// Converts a direction in camera space, x right, y up and z forward, to world space.
+    fn to_world(&self, local: Vec3) -> Vec3 {
+        self.right * local.x + self.up * local.y + self.forward() * local.z
+    }
+
+    /// The ray through the point `(x, y)` on the film passing the lens at the point sampled
+    /// from `u`, at the time sampled from `u_time` while the shutter is open.
+    /// All rays through a film point meet again on the plane in focus.
+    /// https://pbr-book.org/3ed-2018/Camera_Models/Projective_Camera_Models#TheThinLensModelandDepthofField
+    pub fn sample_ray(&self, x: f64, y: f64, u: (f64, f64), u_time: f64) -> Ray {
+        let time = self.shutter.left + (self.shutter.right - self.shutter.left) * u_time;
+        let ray = self.ray(x, y).with_time(time);
+        if self.aperture_radius <= 0.0 || !self.projection.is_planar() {
+            return ray;
+        }
+        let focus = ray.at(self.focus_distance / ray.dir.dot(self.forward()));
+        let (lens_x, lens_y) = self.aperture.sample(u);
+        let origin = ray.origin
+            + self.right * (lens_x * self.aperture_radius)
+            + self.up * (lens_y * self.aperture_radius);
+        Ray::new(origin, (focus - origin).normalize().unwrap()).with_time(time)
+    }
+
+    /// Width at its origin and spread of the cone of rays through a pixel `size` wide
+    /// at the point `(x, y)` on the film, see [`Ray::with_cone`].
+    /// Measured against the rays through the neighboring pixels on both sides, keeping the
+    /// narrower cone so seams of the projection, like cube map edges, don't blur textures.
+    pub fn pixel_cone(&self, x: f64, y: f64, size: f64) -> (f64, f64) {
+        let center = self.ray(x, y);
+        [x - size, x + size]
+            .into_iter()
+            .map(|x| {
+                let neighbor = self.ray(x, y);
+                let width = (neighbor.origin - center.origin).len();
+                let spread = (Vec3::from(neighbor.dir) - Vec3::from(center.dir)).len();
+                (width, spread)
+            })
+            .fold((f64::INFINITY, f64::INFINITY), |(width, spread), cone| {
+                (width.min(cone.0), spread.min(cone.1))
+            })
+    }
+
+    /// The rays through the centers of the pixels, rows from top to bottom.
+    /// The intervals bound each pixel in film coordinates.
     pub fn get_rays(
         &self,
         image_width: u32,
         image_height: u32,
     ) -> impl Iterator<Item = UpRightBoundedRay> + '_ {
+        let aspect_ratio = self
+            .aspect_ratio
+            .or(self.projection.aspect_ratio())
+            .unwrap_or(image_width as f64 / image_height as f64);
+        let film_x = move |x: f64| (2.0 * x / image_width as f64 - 1.0) * aspect_ratio;
+        let film_y = move |y: f64| 1.0 - 2.0 * y / image_height as f64;
         (0..image_width * image_height).map(move |i| {
             let x = (i % image_width) as f64;
-            let y = (i / image_height) as f64;
-            let dist_right = |x| (2.0 * (x + 0.5) / image_height as f64 - 1.0) * self.focal_length;
-            let dist_up = |y| (2.0 * (y + 0.5) / image_height as f64 - 1.0) * self.focal_length;
-            let right = self.right * dist_right(x);
-            let up = self.up * dist_up(y);
-            let pnt = self.center + right + up;
-            let ray = Ray::new(pnt, self.up.cross(self.right).normalize().unwrap());
+            let y = (i / image_width) as f64;
             UpRightBoundedRay::new(
-                ray,
+                self.ray(film_x(x + 0.5), film_y(y + 0.5)),
                 self.up,
                 self.right,
-                Interval::new(dist_right(0.0), dist_right(1.0)),
-                Interval::new(dist_up(0.0), dist_up(1.0)),
+                Interval::new(film_y(y + 1.0), film_y(y)),
+                Interval::new(film_x(x), film_x(x + 1.0)),
             )
         })
     }
@@ -86,29 +387,37 @@ impl Camera {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::scene::moving::{Motion, Moving};
+    use crate::scene::sphere::Sphere;
+    use crate::scene::Hittable;
     use crate::vec3::Vec3;
 
     #[test]
-    fn distribution() {
-        let focal_length = 1.0;
-        let image_width = 2;
-        let image_height = 2;
-
-        let dist_right = |x| (2.0 * (x + 0.5) / image_height as f64 - 1.0) * focal_length;
-        let dist_up = |y| (2.0 * (y + 0.5) / image_width as f64 - 1.0) * focal_length;
-
-        assert_eq!(dist_right(0.0), -0.5);
-        assert_eq!(dist_right(1.0), 0.5);
-        assert_eq!(dist_right(2.0), 1.5);
+    fn rays_stay_in_their_pixel() {
+        use crate::image::samplers::sample_cluster::SampleCluster;
+        use crate::image::samplers::{Independent, Sampler};
 
-        assert_eq!(dist_up(0.0), -0.5);
-        assert_eq!(dist_up(1.0), 0.5);
-        assert_eq!(dist_up(2.0), 1.5);
+        let camera = Camera::look_at(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0))
+            .with_projection(Projection::Orthographic { height: 2.0 });
+        let mut sampler = Independent::new(16);
+        for (pixel, ray) in camera.get_rays(4, 2).enumerate() {
+            let (x, y) = ((pixel % 4) as f64, (pixel / 4) as f64);
+            // Pixels are one unit wide, the film spans -2.0..2.0 by 1.0..-1.0
+            let (left, top) = (x - 2.0, 1.0 - y);
+            let cluster = SampleCluster::from_camera_ray(camera.clone(), ray);
+            for index in 0..16 {
+                sampler.start_pixel_sample(pixel as u64, index);
+                let origin = cluster.sample(&mut sampler).origin;
+                assert!((left..=left + 1.0).contains(&origin.x), "{}", pixel);
+                assert!((top - 1.0..=top).contains(&origin.y), "{}", pixel);
+            }
+        }
     }
 
     #[test]
     fn get_rays() {
-        let camera = Camera::look_at(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0));
+        let camera = Camera::look_at(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0))
+            .with_projection(Projection::Orthographic { height: 2.0 });
         assert_eq!(camera.up.x, 0.0);
         assert_eq!(camera.up.y, 1.0);
         assert_eq!(camera.up.z, 0.0);
@@ -118,7 +427,7 @@ mod tests {
         let mut rays = camera.get_rays(2, 2);
         let ray = rays.next().unwrap();
         assert_eq!(ray.ray.origin.x, -0.5);
-        assert_eq!(ray.ray.origin.y, -0.5);
+        assert_eq!(ray.ray.origin.y, 0.5);
         assert_eq!(ray.ray.origin.z, 1.0);
         assert_eq!(ray.ray.dir.x, 0.0);
         assert_eq!(ray.ray.dir.y, 0.0);
@@ -129,35 +438,29 @@ mod tests {
         assert_eq!(ray.right_vec.x, 1.0);
         assert_eq!(ray.right_vec.y, 0.0);
         assert_eq!(ray.right_vec.z, 0.0);
-        assert_eq!(ray.up_interval.left, -0.5);
-        assert_eq!(ray.up_interval.right, 0.5);
-        assert_eq!(ray.right_interval.left, -0.5);
-        assert_eq!(ray.right_interval.right, 0.5);
+        assert_eq!(ray.up_interval.left, 0.0);
+        assert_eq!(ray.up_interval.right, 1.0);
+        assert_eq!(ray.right_interval.left, -1.0);
+        assert_eq!(ray.right_interval.right, 0.0);
 
         let ray = rays.next().unwrap();
         assert_eq!(ray.ray.origin.x, 0.5);
-        assert_eq!(ray.ray.origin.y, -0.5);
+        assert_eq!(ray.ray.origin.y, 0.5);
         assert_eq!(ray.ray.origin.z, 1.0);
         assert_eq!(ray.ray.dir.x, 0.0);
         assert_eq!(ray.ray.dir.y, 0.0);
         assert_eq!(ray.ray.dir.z, -1.0);
-        assert_eq!(ray.up_vec.x, 0.0);
-        assert_eq!(ray.up_vec.y, 1.0);
-        assert_eq!(ray.up_vec.z, 0.0);
-        assert_eq!(ray.right_vec.x, 1.0);
-        assert_eq!(ray.right_vec.y, 0.0);
-        assert_eq!(ray.right_vec.z, 0.0);
-        assert_eq!(ray.up_interval.left, -0.5);
-        assert_eq!(ray.up_interval.right, 0.5);
-        assert_eq!(ray.right_interval.left, -0.5);
-        assert_eq!(ray.right_interval.right, 0.5);
+        assert_eq!(ray.up_interval.left, 0.0);
+        assert_eq!(ray.up_interval.right, 1.0);
+        assert_eq!(ray.right_interval.left, 0.0);
+        assert_eq!(ray.right_interval.right, 1.0);
         let ray = rays.next().unwrap();
         assert_eq!(ray.ray.origin.x, -0.5);
-        assert_eq!(ray.ray.origin.y, 0.5);
+        assert_eq!(ray.ray.origin.y, -0.5);
         assert_eq!(ray.ray.origin.z, 1.0);
         let ray = rays.next().unwrap();
         assert_eq!(ray.ray.origin.x, 0.5);
-        assert_eq!(ray.ray.origin.y, 0.5);
+        assert_eq!(ray.ray.origin.y, -0.5);
         assert_eq!(ray.ray.origin.z, 1.0);
         let ray = rays.next();
         assert!(ray.is_none());
@@ -171,6 +474,252 @@ mod tests {
         assert!(ray.is_none());
     }
 
+    #[test]
+    fn non_square_images() {
+        let camera = Camera::look_at(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0))
+            .with_projection(Projection::Orthographic { height: 2.0 });
+        // Rows are as long as the image is wide, pixels stay square
+        let rays = camera.get_rays(4, 2).collect::<Vec<_>>();
+        assert_eq!(rays.len(), 8);
+        assert_eq!(rays[3].ray.origin, Pnt3::new(1.5, 0.5, 1.0));
+        assert_eq!(rays[4].ray.origin, Pnt3::new(-1.5, -0.5, 1.0));
+        assert_eq!(rays[7].right_interval.right, 2.0);
+
+        // A fixed aspect ratio stretches the view over the image
+        let camera = camera.with_aspect_ratio(1.0);
+        let rays = camera.get_rays(4, 2).collect::<Vec<_>>();
+        assert_eq!(rays[3].ray.origin, Pnt3::new(0.75, 0.5, 1.0));
+    }
+
+    #[test]
+    fn perspective() {
+        let camera = Camera::look_at(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0))
+            .with_projection(Projection::perspective(90.0));
+        let rays = camera.get_rays(3, 3).collect::<Vec<_>>();
+        assert!(rays
+            .iter()
+            .all(|ray| ray.ray.origin == Pnt3::new(0.0, 0.0, 1.0)));
+        assert_eq!(rays[4].ray.dir, UnitVec3::new(0.0, 0.0, -1.0));
+
+        // The edges of the film are at half the field of view
+        let top = camera.ray(0.0, 1.0).dir;
+        assert!((top.y - top.z.abs()).abs() < 1e-12);
+        let left = camera.ray(-1.0, 0.0).dir;
+        assert!((left.x + left.z.abs()).abs() < 1e-12);
+        // Neighbouring rays diverge
+        assert!(rays[3].ray.dir.x < 0.0 && rays[5].ray.dir.x > 0.0);
+        assert!(rays[1].ray.dir.y > 0.0 && rays[7].ray.dir.y < 0.0);
+
+        let narrow = camera.with_projection(Projection::perspective(30.0));
+        let top = narrow.ray(0.0, 1.0).dir;
+        assert!((top.y / -top.z - 15f64.to_radians().tan()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn pixel_cones() {
+        let camera = Camera::look_at(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0))
+            .with_projection(Projection::perspective(90.0));
+        // The film is one unit away from the pinhole at 90°, a pixel spans its size in radians
+        let (width, spread) = camera.pixel_cone(0.0, 0.0, 0.01);
+        assert_eq!(width, 0.0);
+        assert!((spread - 0.01).abs() < 1e-5);
+        // Pixels at the edges of the film cover smaller angles
+        assert!(camera.pixel_cone(0.9, 0.0, 0.01).1 < spread);
+
+        let orthographic = camera
+            .clone()
+            .with_projection(Projection::Orthographic { height: 4.0 });
+        let (width, spread) = orthographic.pixel_cone(0.5, 0.5, 0.01);
+        assert!((width - 0.02).abs() < 1e-12);
+        assert_eq!(spread, 0.0);
+
+        // Across the seam between two cube map faces the cone stays narrow
+        let cubemap = camera.with_projection(Projection::Cubemap);
+        let (_, seam) = cubemap.pixel_cone(0.499, 0.5, 0.01);
+        let (_, face) = cubemap.pixel_cone(0.0, 0.5, 0.01);
+        assert!(seam < face * 2.0);
+    }
+
+    #[test]
+    fn depth_of_field() {
+        let pinhole = Camera::look_at(Pnt3::new(0.0, 0.0, 10.0), Pnt3::new(0.0, 0.0, 0.0));
+        assert_eq!(
+            pinhole.sample_ray(0.3, 0.2, (0.9, 0.1), 0.5),
+            pinhole.ray(0.3, 0.2)
+        );
+
+        for aperture in [
+            Aperture::Circle,
+            Aperture::Polygon {
+                blades: 6,
+                rotation: 0.3,
+            },
+        ] {
+            let camera =
+                Camera::look_at_focused(Pnt3::new(0.0, 0.0, 10.0), Pnt3::new(0.0, 0.0, 0.0), 0.5)
+                    .with_aperture(aperture);
+            let center = camera.ray(0.3, 0.2);
+            let focus = center.at(10.0 / center.dir.dot(camera.forward()));
+            assert!(focus.z.abs() < 1e-9);
+            for u in [(0.1, 0.2), (0.9, 0.5), (0.55, 0.9)] {
+                let ray = camera.sample_ray(0.3, 0.2, u, 0.5);
+                assert_ne!(ray.origin, center.origin);
+                assert!((ray.origin - center.origin).len() <= 0.5 + 1e-12);
+                assert_eq!(ray.origin.z, 10.0);
+                // Every ray through the film point meets the others on the plane in focus
+                let t = -ray.origin.z / ray.dir.z;
+                assert!((ray.at(t) - focus).len() < 1e-9);
+            }
+        }
+
+        // Moving the focus blurs the target
+        let camera =
+            Camera::look_at_focused(Pnt3::new(0.0, 0.0, 10.0), Pnt3::new(0.0, 0.0, 0.0), 0.5)
+                .with_focus_distance(5.0);
+        let a = camera.sample_ray(0.0, 0.0, (0.1, 0.5), 0.5);
+        let b = camera.sample_ray(0.0, 0.0, (0.9, 0.5), 0.5);
+        assert!((a.at(10.0) - b.at(10.0)).len() > 0.1);
+    }
+
+    fn assert_close(a: impl Into<Vec3>, b: impl Into<Vec3>) {
+        let (a, b) = (a.into(), b.into());
+        assert!((a - b).len() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn equirectangular() {
+        let camera = Camera::look_at(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0))
+            .with_projection(Projection::Equirectangular);
+        assert_eq!(camera.projection.aspect_ratio(), Some(2.0));
+        assert_close(camera.ray(0.0, 0.0).dir, camera.forward());
+        assert_close(camera.ray(1.0, 0.0).dir, camera.right);
+        assert_close(camera.ray(-2.0, 0.0).dir, -camera.forward());
+        assert_close(camera.ray(0.0, 1.0).dir, camera.up);
+        assert_close(camera.ray(1.0, -1.0).dir, -camera.up);
+
+        let rays = camera.get_rays(8, 4).collect::<Vec<_>>();
+        assert!(rays.iter().all(|ray| ray.ray.origin == camera.center));
+        // The leftmost and rightmost columns look the same way from both sides
+        assert_close(
+            rays[8].ray.dir,
+            Vec3::new(-rays[15].ray.dir.x, rays[15].ray.dir.y, rays[15].ray.dir.z),
+        );
+
+        // Images of other shapes still cover the whole sphere, stretched
+        let rays = camera.get_rays(4, 3).collect::<Vec<_>>();
+        assert_eq!(rays[0].right_interval.left, -2.0);
+        assert_eq!(rays[3].right_interval.right, 2.0);
+        assert_close(
+            rays[4].ray.dir,
+            Vec3::new(-rays[7].ray.dir.x, rays[7].ray.dir.y, rays[7].ray.dir.z),
+        );
+        let left = rays[4].ray.dir;
+        assert!(left.dot(camera.forward()) < -0.5);
+    }
+
+    #[test]
+    fn fisheye() {
+        let camera = Camera::look_at(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0));
+        for mapping in [FisheyeMapping::Equidistant, FisheyeMapping::Equisolid] {
+            let camera = camera
+                .clone()
+                .with_projection(Projection::fisheye(180.0, mapping));
+            assert_close(camera.ray(0.0, 0.0).dir, camera.forward());
+            assert_close(camera.ray(1.0, 0.0).dir, camera.right);
+            assert_close(camera.ray(0.0, -1.0).dir, -camera.up);
+        }
+        let angle = |mapping| {
+            let camera = camera
+                .clone()
+                .with_projection(Projection::fisheye(180.0, mapping));
+            camera.ray(0.5, 0.0).dir.dot(camera.forward()).acos()
+        };
+        assert!((angle(FisheyeMapping::Equidistant) - PI / 4.0).abs() < 1e-9);
+        assert!(
+            (angle(FisheyeMapping::Equisolid) - 2.0 * (0.5 * (PI / 4.0).sin()).asin()).abs() < 1e-9
+        );
+
+        let full = camera.with_projection(Projection::fisheye(360.0, FisheyeMapping::Equidistant));
+        assert_close(full.ray(0.0, 1.0).dir, -full.forward());
+    }
+
+    #[test]
+    fn cubemap() {
+        let camera = Camera::look_at(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0))
+            .with_projection(Projection::Cubemap);
+        assert_eq!(camera.projection.aspect_ratio(), Some(1.5));
+        let forward = Vec3::from(camera.forward());
+        let (right, up) = (Vec3::from(camera.right), Vec3::from(camera.up));
+        // Face centers
+        assert_close(camera.ray(-1.0, 0.5).dir, -right);
+        assert_close(camera.ray(0.0, 0.5).dir, forward);
+        assert_close(camera.ray(1.0, 0.5).dir, right);
+        assert_close(camera.ray(-1.0, -0.5).dir, -forward);
+        assert_close(camera.ray(0.0, -0.5).dir, up);
+        assert_close(camera.ray(1.0, -0.5).dir, -up);
+        // Neighbouring faces share their edges
+        let edge = |x: f64, y: f64| camera.ray(x, y).dir;
+        assert_close(edge(0.5 - 1e-12, 0.75), edge(0.5, 0.75));
+        assert_close(edge(-0.5 - 1e-12, 0.25), edge(-0.5, 0.25));
+
+        let rays = camera.get_rays(6, 4).collect::<Vec<_>>();
+        assert_eq!(rays.len(), 24);
+        assert!(rays.iter().all(|ray| ray.ray.origin == camera.center));
+    }
+
+    #[test]
+    fn omni_stereo() {
+        let camera = Camera::look_at(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0))
+            .with_projection(Projection::OmniStereo { ipd: 0.064 });
+        let left = camera.ray(0.0, 0.5);
+        let right = camera.ray(0.0, -0.5);
+        assert_close(left.dir, camera.forward());
+        assert_close(right.dir, camera.forward());
+        assert_close(left.origin, camera.center - camera.right * 0.032);
+        assert_close(right.origin, camera.center + camera.right * 0.032);
+        // Looking to the right the left eye is in front
+        let left = camera.ray(0.5, 0.5);
+        assert_close(left.dir, camera.right);
+        assert_close(left.origin, camera.center + camera.forward() * 0.032);
+        assert_close(camera.ray(0.0, 1.0).dir, camera.up);
+        assert_close(camera.ray(0.0, 0.0).dir, -camera.up);
+    }
+
+    #[test]
+    fn lenses_only_focus_planar_projections() {
+        let camera =
+            Camera::look_at_focused(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0), 0.5)
+                .with_projection(Projection::Equirectangular);
+        assert!(!camera.projection.is_planar());
+        assert_eq!(
+            camera.sample_ray(0.3, 0.2, (0.1, 0.9), 0.5),
+            camera.ray(0.3, 0.2)
+        );
+    }
+
+    #[test]
+    fn shutter() {
+        let camera = Camera::look_at(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0));
+        assert_eq!(camera.sample_ray(0.0, 0.0, (0.5, 0.5), 0.7).time, 0.0);
+        let camera = camera.with_shutter(1.0, 1.5).with_aperture_radius(0.1);
+        assert_eq!(camera.sample_ray(0.0, 0.0, (0.5, 0.5), 0.0).time, 1.0);
+        assert_eq!(camera.sample_ray(0.0, 0.0, (0.2, 0.5), 0.5).time, 1.25);
+        assert_eq!(camera.sample_ray(0.0, 0.0, (0.5, 0.5), 1.0).time, 1.5);
+
+        // Objects moving while the shutter is open are seen at different places
+        let sphere = Moving::new(
+            Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 0.5),
+            Motion::Linear(Vec3::new(2.0, 0.0, 0.0)),
+        );
+        let open = camera.sample_ray(0.0, 0.0, (0.5, 0.5), 0.0);
+        let close = camera.sample_ray(0.0, 0.0, (0.5, 0.5), 1.0);
+        // Moving from 0.0 to 1.0, the sphere rests at its end while the shutter is open
+        assert!(sphere.intersect(&open).is_none() && sphere.intersect(&close).is_none());
+        let sphere = sphere.with_interval(1.0, 1.5);
+        assert!(sphere.intersect(&open).is_some());
+        assert!(sphere.intersect(&close).is_none());
+    }
+
     #[test]
     fn camera_look_at() {
         let camera = Camera::look_at(
@@ -1,3 +1,5 @@
+use crate::vec3::Vec3;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: u8,
@@ -50,3 +52,15 @@ impl std::ops::Add for Color {
         }
     }
 }
+
+/// Converts linear radiance to a color, clamping every channel to `[0, 1]`.
+impl From<Vec3> for Color {
+    fn from(radiance: Vec3) -> Self {
+        let to_u8 = |channel: f64| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color {
+            r: to_u8(radiance.x),
+            g: to_u8(radiance.y),
+            b: to_u8(radiance.z),
+        }
+    }
+}
@@ -2,24 +2,36 @@ use contracts::*;
 
 use crate::camera::Camera;
 use crate::color::Color;
-use crate::ray::Ray;
-use crate::scene::sphere::Sphere;
+use crate::integrator::PathTracer;
 use crate::scene::Scene;
-use crate::vec3::{self, RandomInUnitSphere, Vec3};
+use crate::vec3::Vec3;
 use indicatif::ParallelProgressIterator;
 
 use rayon::prelude::*;
 
+pub mod aov;
+pub mod exr;
+pub mod format;
 pub mod samplers;
+pub mod tonemap;
 
-const BOUNCES: u8 = 255;
+use aov::{AovPixel, Aovs};
+use format::Format;
+use samplers::{Sampler, Sobol};
+use tonemap::ToneMapper;
 
+/// Samples per pixel of [`Image::gen_image`] and [`Image::render`].
+pub const DEFAULT_SAMPLES_PER_PIXEL: u32 = 4;
+
+/// A framebuffer of linear radiance, quantized to colors only on export.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Image {
     width: u32,
     height: u32,
     aspect_ratio: f64,
-    pixels: Vec<Color>,
+    /// Average radiance per pixel, rows from top to bottom.
+    pixels: Vec<Vec3>,
+    tone_mapper: ToneMapper,
 }
 
 impl Image {
@@ -31,7 +43,8 @@ impl Image {
             width,
             height,
             aspect_ratio: width as f64 / height as f64,
-            pixels: vec![Color::black(); (width * height) as usize],
+            pixels: vec![Vec3::null(); (width * height) as usize],
+            tone_mapper: Default::default(),
         }
     }
 
@@ -51,121 +64,185 @@ impl Image {
                 .take((width * height * 3u32) as usize)
                 .collect::<Vec<u8>>()
                 .chunks(3)
-                .map(|pixel| pixel.into())
-                .collect::<Vec<Color>>(),
+                .map(|pixel| Vec3::new(pixel[0], pixel[1], pixel[2]) / 255.0)
+                .collect::<Vec<Vec3>>(),
+            tone_mapper: Default::default(),
         }
     }
 
+    /// Renders the scene with the default [`PathTracer`].
     #[requires(width > 0)]
     #[requires(height > 0)]
     #[ensures(ret.pixels.len() == (ret.width * ret.height) as usize)]
     pub fn gen_image(cam: &Camera, scene: &Scene, width: u32, height: u32) -> Image {
+        Image::render(cam, scene, &PathTracer::default(), width, height)
+    }
+
+    /// Renders the scene with the default [`PathTracer`], along with the auxiliary passes.
+    #[requires(width > 0)]
+    #[requires(height > 0)]
+    pub fn gen_image_with_aovs(
+        cam: &Camera,
+        scene: &Scene,
+        width: u32,
+        height: u32,
+    ) -> (Image, Aovs) {
+        let sampler = Sobol::new(DEFAULT_SAMPLES_PER_PIXEL);
+        Image::render_with_aovs(cam, scene, &PathTracer::default(), &sampler, width, height)
+    }
+
+    /// Renders the scene with [`DEFAULT_SAMPLES_PER_PIXEL`] Sobol samples per pixel.
+    #[requires(width > 0)]
+    #[requires(height > 0)]
+    #[ensures(ret.pixels.len() == (ret.width * ret.height) as usize)]
+    pub fn render(
+        cam: &Camera,
+        scene: &Scene,
+        integrator: &PathTracer,
+        width: u32,
+        height: u32,
+    ) -> Image {
+        let sampler = Sobol::new(DEFAULT_SAMPLES_PER_PIXEL);
+        Image::render_with_sampler(cam, scene, integrator, &sampler, width, height)
+    }
+
+    /// Renders the scene, averaging the radiance of the samples of each pixel drawn by `sampler`.
+    #[requires(width > 0)]
+    #[requires(height > 0)]
+    #[ensures(ret.pixels.len() == (ret.width * ret.height) as usize)]
+    pub fn render_with_sampler(
+        cam: &Camera,
+        scene: &Scene,
+        integrator: &PathTracer,
+        sampler: &dyn Sampler,
+        width: u32,
+        height: u32,
+    ) -> Image {
+        Image::render_pixels(cam, scene, integrator, sampler, width, height, false).0
+    }
+
+    /// Renders the scene like [`Image::render_with_sampler`], also tracing the first hits
+    /// of the camera rays into the auxiliary passes.
+    #[requires(width > 0)]
+    #[requires(height > 0)]
+    pub fn render_with_aovs(
+        cam: &Camera,
+        scene: &Scene,
+        integrator: &PathTracer,
+        sampler: &dyn Sampler,
+        width: u32,
+        height: u32,
+    ) -> (Image, Aovs) {
+        let (image, aovs) =
+            Image::render_pixels(cam, scene, integrator, sampler, width, height, true);
+        (image, Aovs::new(width, height, aovs.unwrap()))
+    }
+
+    fn render_pixels(
+        cam: &Camera,
+        scene: &Scene,
+        integrator: &PathTracer,
+        sampler: &dyn Sampler,
+        width: u32,
+        height: u32,
+        with_aovs: bool,
+    ) -> (Image, Option<Vec<AovPixel>>) {
         let ray_gen = cam
             .get_rays(width, height)
             .map(|ray| samplers::sample_cluster::SampleCluster::from_camera_ray(cam.clone(), ray));
 
-        let mut image = Image::noise(&mut random::default(1337), width, height);
+        let mut image = Image::new(width, height);
         let samples_clusters = ray_gen.collect::<Vec<_>>();
-        image.pixels = samples_clusters
+        let (pixels, aovs): (Vec<Vec3>, Vec<Option<AovPixel>>) = samples_clusters
             .into_par_iter()
+            .enumerate()
             .progress_count(image.pixels.len() as u64)
-            .map(|cluster| {
-                let mut rand = random::default(1337);
-                // as random::Source
-                let mut rand: &mut dyn random::Source = &mut rand;
-                let len = cluster.len() as i64;
-                let color = cluster.fold(((0f64, 0f64, 0f64), len), |acc, ray| {
-                    let color =
-                        (1..=BOUNCES).fold(((0.0, 0.0, 0.0), ray, -1i64, true), |acc, _| {
-                            let color = &acc.0;
-                            let ray = &acc.1;
-                            let hits = acc.2;
-                            let has_hit = acc.3;
-                            if !has_hit {
-                                return acc;
-                            }
-
-                            let hit = scene.intersect(&ray);
-                            if let Some(hit) = hit {
-                                let color = (
-                                    (hit.material.color.x * hit.material.albedo
-                                        + hit.material.roughness * rand.read_f64())
-                                    .clamp(0.0, 255.0)
-                                        + color.0,
-                                    (hit.material.color.y * hit.material.albedo).clamp(0.0, 255.0)
-                                        + hit.material.roughness * color.1,
-                                    (hit.material.color.z * hit.material.albedo
-                                        + hit.material.roughness * rand.read_f64())
-                                    .clamp(0.0, 255.0)
-                                        + color.2,
-                                );
-
-                                let hit_point = ray.at(hit.t);
-                                // let hit_normal = hit.normal;
-                                let hit_normal = RandomInUnitSphere::new(rand)
-                                    .map(|normal| {
-                                        let mut normal = normal;
-                                        if normal.dot(&ray.dir) > 0.0 {
-                                            normal = -normal;
-                                        }
-                                        normal
-                                    })
-                                    .find(|normal| {
-                                        let mut normal = normal;
-                                        if normal.dot(&ray.dir) > 0.0 {
-                                            normal = -normal;
-                                        }
-                                        normal.dot(&ray.dir) < 0.0
-                                    })
-                                    .unwrap();
-                                let mut new_ray = Ray::new(hit_point, hit_normal);
-                                new_ray.origin = new_ray.origin + new_ray.dir * 0.0001;
-                                (color, new_ray, hits + 1, true)
-                            } else {
-                                (*color, ray.clone(), hits, false)
-                            }
-                        });
-
-                    let acc_color = acc.0;
-                    let acc_hits = acc.1;
-                    (
-                        (
-                            color.0 .0 + acc_color.0,
-                            color.0 .1 + acc_color.1,
-                            color.0 .2 + acc_color.2,
-                        ),
-                        color.2 + acc_hits,
-                    )
-                });
-                let len = color.1;
-                let color = color.0;
-                Color {
-                    r: (color.0 / len as f64) as u8,
-                    g: (color.1 / len as f64) as u8,
-                    b: (color.2 / len as f64) as u8,
+            .map(|(index, cluster)| {
+                let mut sampler = sampler.clone_box();
+                let samples = sampler.samples_per_pixel();
+                let mut radiance = Vec3::null();
+                let mut rays = Vec::new();
+                for sample in 0..samples {
+                    // The sample values only depend on the pixel, not on the thread rendering it
+                    sampler.start_pixel_sample(index as u64, sample);
+                    let ray = cluster.sample(sampler.as_mut());
+                    radiance = radiance + integrator.radiance(scene, &ray, sampler.as_mut());
+                    if with_aovs {
+                        rays.push(ray);
+                    }
                 }
+                let aov = with_aovs.then(|| AovPixel::trace(scene, &rays));
+                (radiance / samples as f64, aov)
             })
-            .collect::<Vec<Color>>();
-        image
+            .unzip();
+        image.pixels = pixels;
+        let aovs = with_aovs.then(|| aovs.into_iter().flatten().collect());
+        (image, aovs)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The radiance of the pixel in column `x` and row `y`, counted from the top.
+    #[requires(x < self.width && y < self.height)]
+    pub fn pixel(&self, x: u32, y: u32) -> Vec3 {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    #[requires(x < self.width && y < self.height)]
+    pub fn set_pixel(&mut self, x: u32, y: u32, radiance: Vec3) {
+        self.pixels[(y * self.width + x) as usize] = radiance;
+    }
+
+    /// Returns the image exported through `tone_mapper`.
+    pub fn with_tone_mapper(mut self, tone_mapper: ToneMapper) -> Image {
+        self.tone_mapper = tone_mapper;
+        self
+    }
+
+    pub fn tone_mapper(&self) -> &ToneMapper {
+        &self.tone_mapper
     }
 
+    pub fn set_tone_mapper(&mut self, tone_mapper: ToneMapper) {
+        self.tone_mapper = tone_mapper;
+    }
+
+    /// The pixels quantized by the tone mapper.
+    pub fn colors(&self) -> Vec<Color> {
+        self.pixels
+            .iter()
+            .map(|&radiance| self.tone_mapper.map(radiance))
+            .collect()
+    }
+
+    /// Saves the image in the format matching the extension of `filename`,
+    /// see [`Format::from_path`].
     #[invariant(self.pixels.len() == (self.width * self.height) as usize)]
     pub fn save_to_file(&self, filename: &str) -> anyhow::Result<()> {
-        use std::fs::File;
+        self.save(filename, Format::from_path(filename)?)
+    }
+
+    #[invariant(self.pixels.len() == (self.width * self.height) as usize)]
+    pub fn save(&self, path: impl AsRef<std::path::Path>, format: Format) -> anyhow::Result<()> {
         use std::io::Write;
 
-        let mut file = File::create(filename)?;
-        let data = self.pixels.iter().fold(String::new(), |acc, row| {
-            acc + "\n" + format!("{}", row).as_str()
-        });
-        file.write_all(format!("P3\n{} {}\n255\n", self.width, self.height).as_bytes())?;
-        file.write_all(data.as_bytes())?;
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        format.write(self, &mut writer)?;
+        writer.flush()?;
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::camera::{Aperture, Projection};
+    use crate::interval::Interval;
     use crate::vec3::{Pnt3, UnitVec3};
     use crate::{scene, vec3};
 
@@ -191,6 +268,66 @@ mod tests {
         assert_eq!(image.pixels.len(), 1600 * 900);
     }
 
+    #[test]
+    fn radiance_survives_until_export() {
+        let mut image = Image::new(2, 1)
+            .with_tone_mapper(ToneMapper::default().with_encoding(tonemap::Encoding::Linear));
+        image.set_pixel(1, 0, Vec3::new(0.5, 2.0, 40.0));
+        assert_eq!(image.pixel(1, 0), Vec3::new(0.5, 2.0, 40.0));
+        assert_eq!(image.pixel(0, 0), Vec3::null());
+        assert_eq!(
+            image.colors(),
+            vec![Color::black(), Color::from(Vec3::new(0.5, 1.0, 1.0))]
+        );
+    }
+
+    #[test]
+    fn render_with_every_sampler() {
+        let cam = Camera::look_at(Pnt3::new(0.0, 0.0, 5.0), Pnt3::new(0.0, 0.0, 0.0));
+        let mut scene = scene::Scene::new();
+        scene.set_background(Vec3::new(0.25, 0.5, 1.0));
+        let samplers: [Box<dyn Sampler>; 4] = [
+            Box::new(samplers::Independent::new(2)),
+            Box::new(samplers::Stratified::new(2, 2)),
+            Box::new(samplers::Halton::new(3)),
+            Box::new(samplers::Sobol::new(4)),
+        ];
+        for sampler in samplers {
+            let image = Image::render_with_sampler(
+                &cam,
+                &scene,
+                &PathTracer::default(),
+                sampler.as_ref(),
+                4,
+                3,
+            );
+            assert!(image.pixels.iter().all(|p| *p == Vec3::new(0.25, 0.5, 1.0)));
+        }
+    }
+
+    #[test]
+    fn panoramas_of_any_size() {
+        // A large light behind the camera, only seen at the left and right edges
+        let cam = Camera::look_at(Pnt3::new(0.0, 0.0, 0.0), Pnt3::new(0.0, 0.0, -1.0))
+            .with_projection(Projection::Equirectangular);
+        let mut scene = scene::Scene::new();
+        scene.set_background(Vec3::null());
+        scene.add(
+            scene::sphere::Sphere::new(Pnt3::new(0.0, 0.0, 10.0), 8.0).with_material(
+                crate::material::Material {
+                    albedo: 0.0,
+                    emission: Vec3::new(1.0, 1.0, 1.0),
+                    ..Default::default()
+                },
+            ),
+        );
+        let image = Image::gen_image(&cam, &scene, 4, 3);
+        let middle_row = &image.pixels[4..8];
+        assert!(middle_row[0].x > 0.0 && middle_row[3].x > 0.0);
+        assert_eq!(middle_row[1], Vec3::null());
+        assert_eq!(middle_row[2], Vec3::null());
+    }
+
     #[test]
     fn save_noise_to_file() {
         let mut rand = random::default(1337);
@@ -223,9 +360,15 @@ mod tests {
                 y: 0.0,
                 z: 0.0,
             },
+            projection: Projection::Orthographic { height: 2.0 },
+            aspect_ratio: None,
+            aperture_radius: 0.0,
+            aperture: Aperture::Circle,
+            focus_distance: 1.0,
+            shutter: Interval::new(0.0, 0.0),
         };
         let mut scene = scene::Scene::new();
-        scene.add_sphere(scene::sphere::Sphere::new(
+        scene.add(scene::sphere::Sphere::new(
             vec3::Pnt3 {
                 x: 0.0,
                 y: 0.0,
@@ -233,7 +376,7 @@ mod tests {
             },
             100.0,
         ));
-        scene.add_cube(scene::cube::Cube::new(
+        scene.add(scene::cube::Cube::new(
             vec3::Pnt3 {
                 x: 0.0,
                 y: 0.0,
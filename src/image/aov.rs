@@ -0,0 +1,304 @@
+//! Arbitrary output variables, auxiliary passes describing what the camera rays hit first.
+//! Useful for debugging geometry, and as input to compositing and denoising.
+
+use std::path::Path;
+
+use contracts::*;
+
+use crate::image::exr::{Exr, PixelType};
+use crate::image::tonemap::{Encoding, ToneMapper};
+use crate::image::Image;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use crate::vec3::Vec3;
+
+/// An auxiliary pass.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Aov {
+    /// Distance from the camera along the ray.
+    Depth,
+    /// Shading normal in world space.
+    Normal,
+    /// Fraction of light diffusely reflected, see [`crate::material::Material::reflectance`].
+    Albedo,
+    /// Index into [`Scene::objects`] of the object hit.
+    ObjectId,
+    /// Number of camera rays through the pixel that hit anything.
+    HitCount,
+    /// Texture coordinates.
+    Uv,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 6] = [
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::ObjectId,
+        Aov::HitCount,
+        Aov::Uv,
+    ];
+}
+
+/// The first hits of the camera rays through one pixel, averaged over the rays that hit.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct AovPixel {
+    /// Infinite where every ray missed.
+    pub depth: f64,
+    pub normal: Vec3,
+    pub albedo: Vec3,
+    pub uv: (f64, f64),
+    /// The object hit by the first ray that hit anything, ids can't be averaged.
+    pub object: Option<usize>,
+    pub hits: u32,
+}
+
+impl Default for AovPixel {
+    fn default() -> Self {
+        AovPixel {
+            depth: f64::INFINITY,
+            normal: Vec3::null(),
+            albedo: Vec3::null(),
+            uv: (0.0, 0.0),
+            object: None,
+            hits: 0,
+        }
+    }
+}
+
+impl AovPixel {
+    /// Traces the first hit of each of the `rays` through a pixel.
+    pub fn trace(scene: &Scene, rays: &[Ray]) -> AovPixel {
+        let mut pixel = AovPixel {
+            depth: 0.0,
+            ..Default::default()
+        };
+        for ray in rays {
+            let Some((object, hit)) = scene.intersect_object(ray) else {
+                continue;
+            };
+            let p = ray.at(hit.t);
+            let footprint = hit.uv_footprint(ray.footprint(hit.t));
+            let material = hit.material.at(hit.uv, p, footprint);
+            pixel.depth += hit.t;
+            pixel.normal = pixel.normal + material.shading_normal(&hit, p).into();
+            pixel.albedo = pixel.albedo + material.reflectance();
+            pixel.uv = (pixel.uv.0 + hit.uv.0, pixel.uv.1 + hit.uv.1);
+            pixel.object = pixel.object.or(Some(object));
+            pixel.hits += 1;
+        }
+        if pixel.hits == 0 {
+            return AovPixel::default();
+        }
+        let n = pixel.hits as f64;
+        AovPixel {
+            depth: pixel.depth / n,
+            normal: pixel
+                .normal
+                .normalize()
+                .map(Vec3::from)
+                .unwrap_or(Vec3::null()),
+            albedo: pixel.albedo / n,
+            uv: (pixel.uv.0 / n, pixel.uv.1 / n),
+            ..pixel
+        }
+    }
+}
+
+/// All auxiliary passes of a render, pixels in the same order as the [`Image`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct Aovs {
+    width: u32,
+    height: u32,
+    pixels: Vec<AovPixel>,
+}
+
+impl Aovs {
+    #[requires(pixels.len() == (width * height) as usize)]
+    pub fn new(width: u32, height: u32, pixels: Vec<AovPixel>) -> Aovs {
+        Aovs {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[requires(x < self.width && y < self.height)]
+    pub fn pixel(&self, x: u32, y: u32) -> &AovPixel {
+        &self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// A pass as an image for viewing, stored linearly.
+    /// Depth and hit count are normalized by their maximum, normals mapped from -1.0..1.0
+    /// to 0.0..1.0, object ids get random colors, misses are black.
+    pub fn image(&self, aov: Aov) -> Image {
+        let max_depth = self
+            .pixels
+            .iter()
+            .map(|pixel| pixel.depth)
+            .filter(|depth| depth.is_finite())
+            .fold(0.0, f64::max);
+        let max_hits = self
+            .pixels
+            .iter()
+            .map(|pixel| pixel.hits)
+            .max()
+            .unwrap_or(0);
+        let mut image = Image::new(self.width, self.height)
+            .with_tone_mapper(ToneMapper::default().with_encoding(Encoding::Linear));
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pixel = self.pixel(x, y);
+                let value = match aov {
+                    _ if pixel.hits == 0 => Vec3::null(),
+                    Aov::Depth => Vec3::new(1.0, 1.0, 1.0) * (pixel.depth / max_depth),
+                    Aov::Normal => pixel.normal * 0.5 + Vec3::new(0.5, 0.5, 0.5),
+                    Aov::Albedo => pixel.albedo,
+                    Aov::ObjectId => id_color(pixel.object.unwrap_or_default()),
+                    Aov::HitCount => {
+                        Vec3::new(1.0, 1.0, 1.0) * (pixel.hits as f64 / max_hits as f64)
+                    }
+                    Aov::Uv => {
+                        Vec3::new(pixel.uv.0.rem_euclid(1.0), pixel.uv.1.rem_euclid(1.0), 0.0)
+                    }
+                };
+                image.set_pixel(x, y, value);
+            }
+        }
+        image
+    }
+
+    /// Adds every pass to `exr` as 32-bit float channels: `Z` for depth,
+    /// the layers `normal`, `albedo` and `uv`, `objectId` (-1.0 for misses) and `hits`.
+    #[requires(exr.width() == self.width && exr.height() == self.height)]
+    pub fn add_to_exr(&self, exr: &mut Exr) {
+        let channel = |f: &dyn Fn(&AovPixel) -> f64| -> Vec<f32> {
+            self.pixels.iter().map(|pixel| f(pixel) as f32).collect()
+        };
+        exr.add_channel("Z", PixelType::Float, channel(&|p| p.depth));
+        exr.add_channel("normal.X", PixelType::Float, channel(&|p| p.normal.x));
+        exr.add_channel("normal.Y", PixelType::Float, channel(&|p| p.normal.y));
+        exr.add_channel("normal.Z", PixelType::Float, channel(&|p| p.normal.z));
+        exr.add_channel("albedo.R", PixelType::Float, channel(&|p| p.albedo.x));
+        exr.add_channel("albedo.G", PixelType::Float, channel(&|p| p.albedo.y));
+        exr.add_channel("albedo.B", PixelType::Float, channel(&|p| p.albedo.z));
+        exr.add_channel("uv.U", PixelType::Float, channel(&|p| p.uv.0));
+        exr.add_channel("uv.V", PixelType::Float, channel(&|p| p.uv.1));
+        exr.add_channel(
+            "objectId",
+            PixelType::Float,
+            channel(&|p| p.object.map_or(-1.0, |id| id as f64)),
+        );
+        exr.add_channel("hits", PixelType::Float, channel(&|p| p.hits as f64));
+    }
+
+    /// Saves `beauty` in the default layer of an EXR file with all passes next to it.
+    #[requires(beauty.width() == self.width && beauty.height() == self.height)]
+    pub fn save_exr(&self, beauty: &Image, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        use std::io::Write;
+
+        let mut exr = Exr::new(self.width, self.height);
+        exr.add_layer("", PixelType::Half, beauty);
+        self.add_to_exr(&mut exr);
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        exr.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// A random but stable color per object id.
+fn id_color(id: usize) -> Vec3 {
+    let hash = super::samplers::mix_bits(id as u64);
+    let channel = |shift: u32| ((hash >> shift) & 0xff) as f64 / 255.0;
+    Vec3::new(channel(0), channel(8), channel(16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Material;
+    use crate::scene::plane::Plane;
+    use crate::scene::sphere::Sphere;
+    use crate::vec3::{Pnt3, UnitVec3};
+
+    fn scene() -> Scene {
+        let mut scene = Scene::new();
+        scene.add(Plane::new(
+            Pnt3::new(0.0, -1.0, 0.0),
+            UnitVec3::new(0.0, 1.0, 0.0),
+        ));
+        scene.add(
+            Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 1.0).with_material(Material {
+                color: Vec3::new(255.0, 0.0, 0.0),
+                albedo: 0.5,
+                ..Default::default()
+            }),
+        );
+        scene
+    }
+
+    #[test]
+    fn first_hits() {
+        let scene = scene();
+        let down = UnitVec3::new(0.0, -1.0, 0.0);
+        let rays = [
+            Ray::new(Pnt3::new(0.0, 3.0, 0.0), down),
+            Ray::new(Pnt3::new(0.0, 4.0, 0.0), down),
+            Ray::new(Pnt3::new(0.0, 3.0, 0.0), UnitVec3::new(0.0, 1.0, 0.0)),
+        ];
+        let pixel = AovPixel::trace(&scene, &rays);
+        assert_eq!(pixel.hits, 2);
+        assert_eq!(pixel.depth, 2.5);
+        assert_eq!(pixel.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(pixel.albedo, Vec3::new(0.5, 0.0, 0.0));
+        assert_eq!(pixel.object, Some(1));
+
+        let pixel = AovPixel::trace(&scene, &[Ray::new(Pnt3::new(5.0, 3.0, 0.0), down)]);
+        assert_eq!(pixel.object, Some(0));
+        assert_eq!(pixel.albedo, Vec3::new(1.0, 1.0, 1.0));
+
+        let miss = AovPixel::trace(&scene, &rays[2..]);
+        assert_eq!(miss, AovPixel::default());
+        assert!(miss.depth.is_infinite());
+    }
+
+    #[test]
+    fn pass_images() {
+        let scene = scene();
+        let down = UnitVec3::new(0.0, -1.0, 0.0);
+        let pixels = vec![
+            AovPixel::trace(&scene, &[Ray::new(Pnt3::new(0.0, 3.0, 0.0), down)]),
+            AovPixel::trace(&scene, &[Ray::new(Pnt3::new(5.0, 3.0, 0.0), down)]),
+            AovPixel::default(),
+        ];
+        let aovs = Aovs::new(3, 1, pixels);
+        let depth = aovs.image(Aov::Depth);
+        assert_eq!(depth.pixel(0, 0), Vec3::new(0.5, 0.5, 0.5));
+        assert_eq!(depth.pixel(1, 0), Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(depth.pixel(2, 0), Vec3::null());
+        assert_eq!(
+            aovs.image(Aov::Normal).pixel(0, 0),
+            Vec3::new(0.5, 1.0, 0.5)
+        );
+        let ids = aovs.image(Aov::ObjectId);
+        assert_ne!(ids.pixel(0, 0), ids.pixel(1, 0));
+        for aov in Aov::ALL {
+            assert_eq!(aovs.image(aov).pixel(2, 0), Vec3::null());
+        }
+
+        let mut exr = Exr::new(3, 1);
+        aovs.add_to_exr(&mut exr);
+        let mut data = Vec::new();
+        exr.write(&mut data).unwrap();
+        assert!(data.windows(9).any(|w| w == b"objectId\0"));
+    }
+}
@@ -0,0 +1,314 @@
+//! OpenEXR output of linear radiance, uncompressed scanlines with any number of channels.
+//! https://openexr.com/en/latest/OpenEXRFileLayout.html
+
+use std::io::Write;
+
+use contracts::*;
+
+use crate::image::Image;
+
+/// How channel values are stored.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum PixelType {
+    /// 16-bit floats, about three significant digits, the usual choice for color.
+    #[default]
+    Half,
+    /// 32-bit floats, for data like depth that needs the precision.
+    Float,
+}
+
+impl PixelType {
+    fn id(self) -> i32 {
+        match self {
+            PixelType::Half => 1,
+            PixelType::Float => 2,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            PixelType::Half => 2,
+            PixelType::Float => 4,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Channel {
+    name: String,
+    pixel_type: PixelType,
+    /// Rows from top to bottom.
+    values: Vec<f32>,
+}
+
+/// An EXR file being assembled from channels.
+/// Layers group channels by prefix, e.g. `diffuse.R`, `diffuse.G` and `diffuse.B`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Exr {
+    width: u32,
+    height: u32,
+    channels: Vec<Channel>,
+}
+
+impl Exr {
+    #[requires(width > 0 && height > 0)]
+    pub fn new(width: u32, height: u32) -> Exr {
+        Exr {
+            width,
+            height,
+            channels: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Adds a channel with one value per pixel, rows from top to bottom.
+    #[requires(values.len() == (self.width * self.height) as usize)]
+    #[requires(!name.is_empty())]
+    pub fn add_channel(&mut self, name: &str, pixel_type: PixelType, values: Vec<f32>) {
+        self.channels.retain(|channel| channel.name != name);
+        self.channels.push(Channel {
+            name: name.to_string(),
+            pixel_type,
+            values,
+        });
+    }
+
+    /// Adds the radiance of `image` as the channels `R`, `G` and `B` of the layer `layer`,
+    /// without a prefix for the empty layer name that viewers show by default.
+    #[requires(image.width() == self.width && image.height() == self.height)]
+    pub fn add_layer(&mut self, layer: &str, pixel_type: PixelType, image: &Image) {
+        for (i, suffix) in ["R", "G", "B"].into_iter().enumerate() {
+            let name = if layer.is_empty() {
+                suffix.to_string()
+            } else {
+                format!("{}.{}", layer, suffix)
+            };
+            let values = (0..self.height)
+                .flat_map(|y| (0..self.width).map(move |x| image.pixel(x, y)[i] as f32))
+                .collect();
+            self.add_channel(&name, pixel_type, values);
+        }
+    }
+
+    pub fn write(&self, mut w: impl Write) -> anyhow::Result<()> {
+        // Readers expect the channels in alphabetical order
+        let mut channels: Vec<&Channel> = self.channels.iter().collect();
+        channels.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01]);
+        // Version 2, single part scanlines
+        header.extend_from_slice(&2u32.to_le_bytes());
+
+        let mut list = Vec::new();
+        for channel in &channels {
+            list.extend_from_slice(channel.name.as_bytes());
+            list.push(0);
+            list.extend_from_slice(&channel.pixel_type.id().to_le_bytes());
+            // Not perceptually linear, reserved, then x and y sampling
+            list.extend_from_slice(&[0, 0, 0, 0]);
+            list.extend_from_slice(&1i32.to_le_bytes());
+            list.extend_from_slice(&1i32.to_le_bytes());
+        }
+        list.push(0);
+        attribute(&mut header, "channels", "chlist", &list);
+        // No compression
+        attribute(&mut header, "compression", "compression", &[0]);
+        let mut window = Vec::new();
+        for value in [0, 0, self.width as i32 - 1, self.height as i32 - 1] {
+            window.extend_from_slice(&value.to_le_bytes());
+        }
+        attribute(&mut header, "dataWindow", "box2i", &window);
+        attribute(&mut header, "displayWindow", "box2i", &window);
+        // Increasing y
+        attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+        attribute(
+            &mut header,
+            "pixelAspectRatio",
+            "float",
+            &1f32.to_le_bytes(),
+        );
+        attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+        attribute(
+            &mut header,
+            "screenWindowWidth",
+            "float",
+            &1f32.to_le_bytes(),
+        );
+        header.push(0);
+
+        // One scanline per block, each block is its row, its size and its data
+        let line_size: usize = channels
+            .iter()
+            .map(|channel| channel.pixel_type.size() * self.width as usize)
+            .sum();
+        let block_size = 8 + line_size;
+        let first_block = header.len() + 8 * self.height as usize;
+        w.write_all(&header)?;
+        for y in 0..self.height as usize {
+            w.write_all(&((first_block + y * block_size) as u64).to_le_bytes())?;
+        }
+
+        let mut block = Vec::with_capacity(block_size);
+        for y in 0..self.height as usize {
+            block.clear();
+            block.extend_from_slice(&(y as i32).to_le_bytes());
+            block.extend_from_slice(&(line_size as i32).to_le_bytes());
+            for channel in &channels {
+                let row = &channel.values[y * self.width as usize..][..self.width as usize];
+                for &value in row {
+                    match channel.pixel_type {
+                        PixelType::Half => block.extend_from_slice(&to_half(value).to_le_bytes()),
+                        PixelType::Float => block.extend_from_slice(&value.to_le_bytes()),
+                    }
+                }
+            }
+            w.write_all(&block)?;
+        }
+        Ok(())
+    }
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Converts to the bits of an IEEE 754 half precision float, rounding to nearest even.
+/// Out of range values become infinite or zero.
+pub fn to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        // Infinity stays infinite, NaN stays NaN
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+    let rounded = |value: u32, shift: u32| {
+        let rest = value & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let truncated = value >> shift;
+        if rest > halfway || (rest == halfway && truncated & 1 == 1) {
+            truncated + 1
+        } else {
+            truncated
+        }
+    };
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if half_exponent <= 0 {
+        // Subnormal, the implicit leading bit becomes explicit
+        if half_exponent < -10 {
+            return sign;
+        }
+        let shift = (14 - half_exponent) as u32;
+        return sign | rounded(mantissa | 0x80_0000, shift) as u16;
+    }
+    // A carry out of the mantissa correctly bumps the exponent
+    sign | rounded(((half_exponent as u32) << 23) | mantissa, 13) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn half_precision() {
+        assert_eq!(to_half(0.0), 0x0000);
+        assert_eq!(to_half(-0.0), 0x8000);
+        assert_eq!(to_half(1.0), 0x3c00);
+        assert_eq!(to_half(-2.0), 0xc000);
+        assert_eq!(to_half(0.5), 0x3800);
+        assert_eq!(to_half(65504.0), 0x7bff);
+        assert_eq!(to_half(1e6), 0x7c00);
+        assert_eq!(to_half(f32::INFINITY), 0x7c00);
+        assert_eq!(to_half(f32::NAN) & 0x7e00, 0x7e00);
+        // Smallest subnormal and below
+        assert_eq!(to_half(2f32.powi(-24)), 0x0001);
+        assert_eq!(to_half(2f32.powi(-26)), 0x0000);
+        // Rounds to nearest even
+        assert_eq!(to_half(1.0 + 2f32.powi(-11)), 0x3c00);
+        assert_eq!(to_half(1.0 + 3.0 * 2f32.powi(-11)), 0x3c02);
+        assert_eq!(to_half(0.1), 0x2e66);
+    }
+
+    /// Reads the value of `channel` at `(x, y)` from an EXR written by [`Exr::write`].
+    fn read(
+        data: &[u8],
+        width: usize,
+        channels: &[(&str, usize)],
+        channel: &str,
+        x: usize,
+        y: usize,
+    ) -> Vec<u8> {
+        let end = b"screenWindowWidth\0float\0";
+        let header = data.windows(end.len()).position(|w| w == end).unwrap() + end.len() + 8 + 1;
+        let offset = u64::from_le_bytes(data[header + 8 * y..][..8].try_into().unwrap()) as usize;
+        assert_eq!(
+            i32::from_le_bytes(data[offset..][..4].try_into().unwrap()),
+            y as i32
+        );
+        let mut position = offset + 8;
+        for &(name, size) in channels {
+            if name == channel {
+                return data[position + x * size..][..size].to_vec();
+            }
+            position += width * size;
+        }
+        panic!("No channel {}", channel);
+    }
+
+    #[test]
+    fn layers() {
+        let mut image = Image::new(2, 2);
+        image.set_pixel(1, 1, Vec3::new(1.0, 2.0, 1000.5));
+        let mut exr = Exr::new(2, 2);
+        exr.add_layer("", PixelType::Half, &image);
+        exr.add_layer("beauty", PixelType::Float, &image);
+        exr.add_channel("Z", PixelType::Float, vec![0.0, 1.0, 2.0, 3.0]);
+        let mut data = Vec::new();
+        exr.write(&mut data).unwrap();
+
+        assert_eq!(data[..4], [0x76, 0x2f, 0x31, 0x01]);
+        let channels = [
+            ("B", 2),
+            ("G", 2),
+            ("R", 2),
+            ("Z", 4),
+            ("beauty.B", 4),
+            ("beauty.G", 4),
+            ("beauty.R", 4),
+        ];
+        let size: usize = channels.iter().map(|(_, size)| size * 2).sum();
+        assert!(data.len() > 2 * (8 + size));
+        assert_eq!(
+            read(&data, 2, &channels, "G", 1, 1),
+            to_half(2.0).to_le_bytes()
+        );
+        assert_eq!(
+            read(&data, 2, &channels, "R", 0, 1),
+            to_half(0.0).to_le_bytes()
+        );
+        assert_eq!(
+            read(&data, 2, &channels, "beauty.B", 1, 1),
+            1000.5f32.to_le_bytes()
+        );
+        assert_eq!(read(&data, 2, &channels, "Z", 0, 1), 2f32.to_le_bytes());
+    }
+}
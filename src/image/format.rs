@@ -0,0 +1,230 @@
+//! File formats images are saved in.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::bail;
+
+use crate::image::exr::{Exr, PixelType};
+use crate::image::Image;
+
+/// A file format. Low dynamic range formats have the pixels quantized by the image's
+/// tone mapper, high dynamic range formats store the radiance as is.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    /// ASCII PPM (P3), readable by eye but large.
+    /// https://netpbm.sourceforge.net/doc/ppm.html
+    PlainPpm,
+    /// Binary PPM (P6), 8 bits per channel.
+    BinaryPpm,
+    /// PNG with 8 bits per channel.
+    /// https://www.w3.org/TR/png/
+    Png8,
+    /// PNG with 16 bits per channel, for further editing without banding.
+    Png16,
+    /// Portable Float Map, 32-bit float radiance.
+    /// https://www.pauldebevec.com/Research/HDR/PFM/
+    Pfm,
+    /// OpenEXR with the radiance in the default `R`, `G` and `B` channels.
+    /// See [`Exr`] for multiple layers.
+    Exr(PixelType),
+}
+
+impl Format {
+    /// The format for a file name by its extension: `.ppm` is binary PPM, `.plain.ppm` plain PPM,
+    /// `.png` 8-bit PNG, `.pfm` PFM and `.exr` half float OpenEXR.
+    pub fn from_path(path: impl AsRef<Path>) -> anyhow::Result<Format> {
+        let path = path.as_ref();
+        let plain = path
+            .file_stem()
+            .and_then(|stem| Path::new(stem).extension())
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("plain"));
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("ppm") if plain => Ok(Format::PlainPpm),
+            Some("ppm") => Ok(Format::BinaryPpm),
+            Some("png") => Ok(Format::Png8),
+            Some("pfm") => Ok(Format::Pfm),
+            Some("exr") => Ok(Format::Exr(PixelType::Half)),
+            _ => bail!("No image format for {}", path.display()),
+        }
+    }
+
+    /// Writes `image` encoded in this format.
+    pub fn write(self, image: &Image, w: impl Write) -> anyhow::Result<()> {
+        match self {
+            Format::PlainPpm => write_plain_ppm(image, w),
+            Format::BinaryPpm => write_binary_ppm(image, w),
+            Format::Png8 => write_png(image, w, png::BitDepth::Eight),
+            Format::Png16 => write_png(image, w, png::BitDepth::Sixteen),
+            Format::Pfm => write_pfm(image, w),
+            Format::Exr(pixel_type) => {
+                let mut exr = Exr::new(image.width(), image.height());
+                exr.add_layer("", pixel_type, image);
+                exr.write(w)
+            }
+        }
+    }
+}
+
+fn write_plain_ppm(image: &Image, mut w: impl Write) -> anyhow::Result<()> {
+    write!(w, "P3\n{} {}\n255", image.width(), image.height())?;
+    for color in image.colors() {
+        write!(w, "\n{}", color)?;
+    }
+    Ok(())
+}
+
+fn write_binary_ppm(image: &Image, mut w: impl Write) -> anyhow::Result<()> {
+    write!(w, "P6\n{} {}\n255\n", image.width(), image.height())?;
+    let data: Vec<u8> = image.colors().iter().flat_map(|c| c.to_rgb()).collect();
+    w.write_all(&data)?;
+    Ok(())
+}
+
+fn write_png(image: &Image, w: impl Write, depth: png::BitDepth) -> anyhow::Result<()> {
+    let mut encoder = png::Encoder::new(w, image.width(), image.height());
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(depth);
+    if image.tone_mapper().encoding == crate::image::tonemap::Encoding::Srgb {
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    }
+    let data: Vec<u8> = match depth {
+        png::BitDepth::Sixteen => {
+            let mapper = image.tone_mapper();
+            let to_u16 = |x: f64| (mapper.map_channel(x) * 65535.0).round() as u16;
+            (0..image.height())
+                .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+                .flat_map(|(x, y)| {
+                    let radiance = image.pixel(x, y);
+                    [radiance.x, radiance.y, radiance.z]
+                })
+                // Most significant byte first
+                .flat_map(|channel| to_u16(channel).to_be_bytes())
+                .collect()
+        }
+        _ => image.colors().iter().flat_map(|c| c.to_rgb()).collect(),
+    };
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+fn write_pfm(image: &Image, mut w: impl Write) -> anyhow::Result<()> {
+    // A negative scale marks little endian floats
+    write!(w, "PF\n{} {}\n-1.0\n", image.width(), image.height())?;
+    let mut data = Vec::with_capacity((image.width() * image.height() * 12) as usize);
+    // Rows from bottom to top
+    for y in (0..image.height()).rev() {
+        for x in 0..image.width() {
+            let radiance = image.pixel(x, y);
+            for channel in [radiance.x, radiance.y, radiance.z] {
+                data.extend_from_slice(&(channel as f32).to_le_bytes());
+            }
+        }
+    }
+    w.write_all(&data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::tonemap::{Encoding, ToneMapper};
+    use crate::texture::{ColorSpace, ImageTexture};
+    use crate::vec3::Vec3;
+
+    fn gradient() -> Image {
+        let mut image = Image::new(3, 2)
+            .with_tone_mapper(ToneMapper::default().with_encoding(Encoding::Linear));
+        for y in 0..2 {
+            for x in 0..3 {
+                image.set_pixel(x, y, Vec3::new(x as f64 / 2.0, y as f64, 0.2));
+            }
+        }
+        image
+    }
+
+    fn encode(image: &Image, format: Format) -> Vec<u8> {
+        let mut data = Vec::new();
+        format.write(image, &mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn formats_by_extension() {
+        assert_eq!(Format::from_path("a/b.ppm").unwrap(), Format::BinaryPpm);
+        assert_eq!(
+            Format::from_path("a/b.plain.ppm").unwrap(),
+            Format::PlainPpm
+        );
+        assert_eq!(
+            Format::from_path("a.plain/b.ppm").unwrap(),
+            Format::BinaryPpm
+        );
+        assert!(Format::from_path("render.plain").is_err());
+        assert_eq!(Format::from_path("render.PNG").unwrap(), Format::Png8);
+        assert_eq!(Format::from_path("render.pfm").unwrap(), Format::Pfm);
+        assert_eq!(
+            Format::from_path("render.exr").unwrap(),
+            Format::Exr(PixelType::Half)
+        );
+        assert!(Format::from_path("render.txt").is_err());
+        assert!(Format::from_path("render").is_err());
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let image = gradient();
+        let plain =
+            ImageTexture::from_ppm(&encode(&image, Format::PlainPpm), ColorSpace::Linear).unwrap();
+        let binary_data = encode(&image, Format::BinaryPpm);
+        assert_eq!(binary_data.len(), "P6\n3 2\n255\n".len() + 3 * 2 * 3);
+        let binary = ImageTexture::from_ppm(&binary_data, ColorSpace::Linear).unwrap();
+        assert_eq!(plain, binary);
+        assert_eq!(binary.pixel(2, 1), Vec3::new(255.0, 255.0, 51.0));
+        assert_eq!(binary.pixel(1, 0), Vec3::new(128.0, 0.0, 51.0));
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let image = gradient();
+        let png8 =
+            ImageTexture::from_png(&encode(&image, Format::Png8), ColorSpace::Linear).unwrap();
+        assert_eq!((png8.width(), png8.height()), (3, 2));
+        assert_eq!(png8.pixel(1, 0), Vec3::new(128.0, 0.0, 51.0));
+
+        // Sixteen bits keep the exact half
+        let png16 =
+            ImageTexture::from_png(&encode(&image, Format::Png16), ColorSpace::Linear).unwrap();
+        let half = 32768.0 * 255.0 / 65535.0;
+        assert!((png16.pixel(1, 0).x - half).abs() < 1e-9);
+        assert_eq!(png16.pixel(2, 1).y, 255.0);
+    }
+
+    #[test]
+    fn pfm_keeps_the_radiance() {
+        let mut image = gradient();
+        image.set_pixel(0, 1, Vec3::new(100.0, -1.0, 0.125));
+        let data = encode(&image, Format::Pfm);
+        let header = b"PF\n3 2\n-1.0\n";
+        assert_eq!(data[..header.len()], header[..]);
+        assert_eq!(data.len(), header.len() + 3 * 2 * 12);
+        // The bottom row comes first
+        let float =
+            |i: usize| f32::from_le_bytes(data[header.len() + 4 * i..][..4].try_into().unwrap());
+        assert_eq!((float(0), float(1), float(2)), (100.0, -1.0, 0.125));
+        assert_eq!(float(9 + 3), 0.5);
+    }
+
+    #[test]
+    fn exr_by_extension() {
+        let data = encode(&gradient(), Format::Exr(PixelType::Float));
+        assert_eq!(data[..4], [0x76, 0x2f, 0x31, 0x01]);
+        assert!(data.windows(2).any(|w| w == b"R\0"));
+    }
+}
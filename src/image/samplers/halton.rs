@@ -0,0 +1,140 @@
+use contracts::*;
+
+use crate::image::samplers::{hash, permute, to_unit, Sampler};
+
+/// Bases of the dimensions, dimensions beyond fall back to independent random values.
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// The Halton sequence, the radical inverse of the sample index in a prime base per dimension.
+/// Digits are permuted randomly per pixel and dimension, decorrelating pixels
+/// while keeping every base stratified.
+/// https://pbr-book.org/4ed/Sampling_and_Reconstruction/Halton_Sampler
+#[derive(Debug, Clone)]
+pub struct Halton {
+    samples_per_pixel: u32,
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl Halton {
+    #[requires(samples_per_pixel > 0)]
+    pub fn new(samples_per_pixel: u32) -> Halton {
+        Halton {
+            samples_per_pixel,
+            seed: 0,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// Returns the sampler generating other values, e.g. for another frame.
+    pub fn with_seed(mut self, seed: u64) -> Halton {
+        self.seed = seed;
+        self
+    }
+}
+
+/// The digits of `index` in `base` mirrored at the decimal point,
+/// each digit permuted by a permutation picked by `seed` and its position.
+fn scrambled_radical_inverse(base: u32, index: u64, seed: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_m = 1.0;
+    let mut result = 0.0;
+    let mut rest = index;
+    let mut digit_index = 0;
+    // Zero digits past the last of the index are permuted too, until they no longer matter
+    while (base - 1) as f64 * inv_base_m > f64::EPSILON {
+        let digit = (rest % base as u64) as u32;
+        rest /= base as u64;
+        let permuted = permute(digit, base, hash(&[seed, digit_index]) as u32);
+        inv_base_m *= inv_base;
+        result += permuted as f64 * inv_base_m;
+        digit_index += 1;
+    }
+    result.min(1.0 - f64::EPSILON / 2.0)
+}
+
+impl Sampler for Halton {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let seed = hash(&[self.pixel, self.dimension, self.seed]);
+        let value = match PRIMES.get(self.dimension as usize) {
+            Some(&base) => scrambled_radical_inverse(base, self.index as u64, seed),
+            None => to_unit(hash(&[seed, self.index as u64])),
+        };
+        self.dimension += 1;
+        value
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radical_inverse() {
+        // The first digit decides the half, the second the quarter within
+        let mut quarters = [0; 4];
+        for index in 0..4 {
+            let value = scrambled_radical_inverse(2, index, 7);
+            assert!((0.0..1.0).contains(&value));
+            quarters[(value * 4.0) as usize] += 1;
+        }
+        assert_eq!(quarters, [1; 4]);
+        assert_ne!(
+            scrambled_radical_inverse(3, 5, 7),
+            scrambled_radical_inverse(3, 5, 8)
+        );
+    }
+
+    #[test]
+    fn stratified_in_every_base() {
+        let mut sampler = Halton::new(9);
+        let mut base_two = [0; 8];
+        let mut base_three = [0; 9];
+        for index in 0..9 {
+            sampler.start_pixel_sample(17, index);
+            let (x, y) = sampler.get_2d();
+            if index < 8 {
+                base_two[(x * 8.0) as usize] += 1;
+            }
+            base_three[(y * 9.0) as usize] += 1;
+        }
+        assert_eq!(base_two, [1; 8]);
+        assert_eq!(base_three, [1; 9]);
+
+        // Pixels are scrambled differently
+        sampler.start_pixel_sample(17, 0);
+        let a = sampler.get_1d();
+        sampler.start_pixel_sample(18, 0);
+        assert_ne!(sampler.get_1d(), a);
+    }
+
+    #[test]
+    fn falls_back_beyond_the_primes() {
+        let mut sampler = Halton::new(4);
+        sampler.start_pixel_sample(0, 0);
+        for _ in 0..PRIMES.len() + 4 {
+            assert!((0.0..1.0).contains(&sampler.get_1d()));
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use contracts::*;
+
+use crate::image::samplers::{hash, to_unit, Sampler};
+
+/// Independent uniform random values, white noise.
+/// The baseline every other sampler improves on, and the only one without any structure
+/// that could alias with the scene.
+#[derive(Clone)]
+pub struct Independent {
+    samples_per_pixel: u32,
+    seed: u64,
+    rand: random::Default,
+}
+
+impl Independent {
+    #[requires(samples_per_pixel > 0)]
+    pub fn new(samples_per_pixel: u32) -> Independent {
+        Independent {
+            samples_per_pixel,
+            seed: 0,
+            rand: random::default(0),
+        }
+    }
+
+    /// Returns the sampler generating other values, e.g. for another frame.
+    pub fn with_seed(mut self, seed: u64) -> Independent {
+        self.seed = seed;
+        self
+    }
+}
+
+impl Sampler for Independent {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32) {
+        self.rand = random::default(hash(&[pixel, index as u64, self.seed]));
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        to_unit(random::Source::read_u64(&mut self.rand))
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reproducible_per_pixel_sample() {
+        let mut sampler = Independent::new(4);
+        let mut values = |pixel, index| {
+            sampler.start_pixel_sample(pixel, index);
+            [sampler.get_1d(), sampler.get_1d(), sampler.get_1d()]
+        };
+        let a = values(10, 2);
+        assert!(a.iter().all(|u| (0.0..1.0).contains(u)));
+        assert_ne!(a[0], a[1]);
+        assert_eq!(values(10, 2), a);
+        assert_ne!(values(11, 2), a);
+        assert_ne!(values(10, 3), a);
+
+        let mut other = Independent::new(4).with_seed(1);
+        other.start_pixel_sample(10, 2);
+        assert_ne!(other.get_1d(), a[0]);
+    }
+}
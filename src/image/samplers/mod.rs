@@ -1 +1,131 @@
+//! Sample values for the pixel samples of a render.
+//! https://pbr-book.org/4ed/Sampling_and_Reconstruction/Sampling_Interface
+
+pub mod halton;
+pub mod independent;
 pub mod sample_cluster;
+pub mod sobol;
+pub mod stratified;
+
+pub use self::halton::Halton;
+pub use self::independent::Independent;
+pub use self::sobol::Sobol;
+pub use self::stratified::Stratified;
+
+/// Generates the values of every pixel sample, one dimension after another.
+///
+/// A pixel sample uses its dimensions in a fixed order: the position within the pixel,
+/// the point on the lens, the time, then whatever the integrator reads, e.g. to sample BSDFs
+/// and lights. Samplers spreading the samples of a pixel evenly over these dimensions reach
+/// a noise level with fewer samples than independent random numbers do.
+pub trait Sampler: Send + Sync {
+    fn samples_per_pixel(&self) -> u32;
+
+    /// Starts sample `index` of the pixel with the index `pixel`, back at the first dimension.
+    /// The values only depend on the pixel, the sample and the dimension,
+    /// not on which thread renders the pixel in which order.
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32);
+
+    /// The value of the next dimension, in `[0, 1)`.
+    fn get_1d(&mut self) -> f64;
+
+    /// The values of the next two dimensions, stratified together where the sampler can.
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+
+    /// A copy for rendering pixels on another thread.
+    fn clone_box(&self) -> Box<dyn Sampler>;
+}
+
+/// Scrambles the bits of `value` into a well distributed hash.
+/// https://prng.di.unimi.it/splitmix64.c
+pub fn mix_bits(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Hashes several values, e.g. a pixel, a dimension and a seed.
+fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0, |hash, value| mix_bits(hash.rotate_left(23) ^ *value))
+}
+
+/// The value in `[0, 1)` of the upper 53 bits.
+fn to_unit(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Element `index` of a random permutation of `0..len` picked by `seed`,
+/// without building the permutation.
+/// Andrew Kensler, Correlated Multi-Jittered Sampling, https://graphics.pixar.com/library/MultiJitteredSampling/
+fn permute(index: u32, len: u32, seed: u32) -> u32 {
+    if len <= 1 {
+        return 0;
+    }
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    let p = seed;
+    let mut i = index;
+    // Cycle walking, permuting within the next power of two until the result fits
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < len {
+            break;
+        }
+    }
+    ((i as u64 + p as u64) % len as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permutations() {
+        for len in [1, 2, 3, 7, 16, 100] {
+            for seed in [0, 1, 0xdeadbeef] {
+                let mut seen = vec![false; len as usize];
+                for i in 0..len {
+                    let element = permute(i, len, seed);
+                    assert!(!seen[element as usize], "{} {}", len, seed);
+                    seen[element as usize] = true;
+                }
+            }
+        }
+        let a = (0..16).map(|i| permute(i, 16, 1)).collect::<Vec<_>>();
+        let b = (0..16).map(|i| permute(i, 16, 2)).collect::<Vec<_>>();
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn unit_values() {
+        assert_eq!(to_unit(0), 0.0);
+        assert!(to_unit(u64::MAX) < 1.0);
+        assert_eq!(to_unit(1 << 63), 0.5);
+    }
+}
@@ -1,118 +1,78 @@
 use crate::camera::Camera;
+use crate::image::samplers::Sampler;
 use crate::interval::Interval;
 use crate::ray::{Ray, UpRightBoundedRay};
 
-/// A cluster of rays that are close to each other.
+/// A cluster of rays that are close to each other, the rays through one pixel.
 pub struct SampleCluster {
     pub ray: UpRightBoundedRay,
     pub camera: Camera,
-    rand: random::Default,
-    current_iteration: usize,
 }
 
 impl SampleCluster {
-    const CLUSTER_SIZE: usize = 4;
-    /// Creates a new sample cluster from a camera and a ray.
-    /// Returns an iterator over the rays in the cluster.
-    pub fn from_camera_ray(
-        camera: Camera,
-        ray: UpRightBoundedRay,
-    ) -> impl Iterator<Item = Ray> + (ExactSizeIterator) + Send {
-        let cluster = SampleCluster {
-            rand: random::default(ray.ray.origin.x as u64),
-            ray,
-            camera,
-            current_iteration: 0,
-        };
-        cluster
+    /// Creates a new sample cluster from a camera and the ray through a pixel's center.
+    pub fn from_camera_ray(camera: Camera, ray: UpRightBoundedRay) -> SampleCluster {
+        SampleCluster { ray, camera }
     }
 
-    /// Samples from the interval with the given random number generator.
-    /// Returns a value in the interval.
-    fn sample_from(interval: &Interval<f64>, rand: &mut impl random::Source) -> f64 {
+    /// Maps `u` between 0.0 and 1.0 into the interval.
+    fn sample_from(interval: &Interval<f64>, u: f64) -> f64 {
         let lower = interval.left;
         let upper = interval.right;
-        let rand_num = rand.read::<f64>();
-        lower + (upper - lower) * rand_num
+        lower + (upper - lower) * u
     }
-}
-
-// Impl Iterator instead of returning an iterator
-impl Iterator for SampleCluster {
-    type Item = Ray;
-
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        (Self::CLUSTER_SIZE, Some(Self::CLUSTER_SIZE))
-    }
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.current_iteration >= Self::CLUSTER_SIZE {
-            return None;
-        }
-        self.current_iteration += 1;
-
-        // Some(self.ray.ray.clone())
-
-        let x = SampleCluster::sample_from(&self.ray.right_interval, &mut self.rand);
-        let y = SampleCluster::sample_from(&self.ray.up_interval, &mut self.rand);
-        // println!(
-        //     "x: {}, y: {}, interval: {:?}",
-        //     x, y, self.ray.up_interval
-        // );
-
-        let right = self.camera.right * x;
-        let up = self.camera.up * y;
-        let pnt = self.ray.ray.origin + right + up;
-        let ray = Ray::new(pnt, self.ray.ray.dir);
-
-        Some(ray)
-    }
-}
 
-impl ExactSizeIterator for SampleCluster {
-    fn len(&self) -> usize {
-        Self::CLUSTER_SIZE
+    /// The ray of the current pixel sample of `sampler`, drawing the position within the pixel,
+    /// then the point on the lens, then the time.
+    /// The ray stands for a cone as wide as the pixel, to filter textures by.
+    pub fn sample(&self, sampler: &mut dyn Sampler) -> Ray {
+        let (u, v) = sampler.get_2d();
+        let x = SampleCluster::sample_from(&self.ray.right_interval, u);
+        let y = SampleCluster::sample_from(&self.ray.up_interval, v);
+        let lens = sampler.get_2d();
+        let time = sampler.get_1d();
+        let size = self.ray.right_interval.right - self.ray.right_interval.left;
+        let (width, spread) = self.camera.pixel_cone(x, y, size);
+        self.camera
+            .sample_ray(x, y, lens, time)
+            .with_cone(width, spread)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    struct MockSource {
-        pub rand: u64,
-    }
-
-    impl random::Source for MockSource {
-        fn read_u64(&mut self) -> u64 {
-            let tmp = self.rand;
-            if self.rand == std::u64::MAX {
-                self.rand = 0;
-            } else {
-                self.rand += 1;
-            }
-            tmp
-        }
-    }
+    use crate::image::samplers::Stratified;
+    use crate::vec3::Pnt3;
 
     #[test]
     fn sample_from() {
-        let mut source = MockSource { rand: 0 };
         let interval = Interval::new(-1.0, 1.0);
-        let result = SampleCluster::sample_from(&interval, &mut source);
+        let result = SampleCluster::sample_from(&interval, 0.0);
         assert_eq!(result, -1.0);
 
-        let mut source = MockSource {
-            rand: std::u64::MAX,
-        };
-        let result = SampleCluster::sample_from(&interval, &mut source);
+        let result = SampleCluster::sample_from(&interval, 1.0);
         assert_eq!(result, 1.0);
 
-        let mut source = MockSource {
-            rand: std::u64::MAX / 2,
-        };
-        let result = SampleCluster::sample_from(&interval, &mut source);
+        let result = SampleCluster::sample_from(&interval, 0.5);
         assert_eq!(result, 0.0);
     }
-}
 
+    #[test]
+    fn rays_stay_in_their_pixel() {
+        let camera = Camera::look_at(Pnt3::new(0.0, 0.0, 1.0), Pnt3::new(0.0, 0.0, 0.0))
+            .with_projection(crate::camera::Projection::Orthographic { height: 2.0 });
+        let pixel = camera.get_rays(2, 2).nth(1).unwrap();
+        let cluster = SampleCluster::from_camera_ray(camera, pixel);
+        let mut sampler = Stratified::new(2, 2);
+        let mut quadrants = [[0; 2]; 2];
+        for index in 0..4 {
+            sampler.start_pixel_sample(1, index);
+            let ray = cluster.sample(&mut sampler);
+            assert!((0.0..=1.0).contains(&ray.origin.x));
+            assert!((0.0..=1.0).contains(&ray.origin.y));
+            quadrants[(ray.origin.y * 2.0) as usize][(ray.origin.x * 2.0) as usize] += 1;
+        }
+        assert_eq!(quadrants, [[1; 2]; 2]);
+    }
+}
@@ -0,0 +1,176 @@
+use contracts::*;
+
+use crate::image::samplers::{hash, mix_bits, permute, Sampler};
+
+/// Generator matrix of the second Sobol dimension, the first is the van der Corput sequence.
+/// From the primitive polynomial `x + 1`, every direction number is the previous one
+/// xored with itself shifted by one.
+const SECOND_DIMENSION: [u32; 32] = {
+    let mut directions = [0; 32];
+    directions[0] = 1 << 31;
+    let mut i = 1;
+    while i < 32 {
+        directions[i] = directions[i - 1] ^ (directions[i - 1] >> 1);
+        i += 1;
+    }
+    directions
+};
+
+/// The first two dimensions of the Sobol sequence, padded to any number of dimensions:
+/// every dimension or pair of dimensions uses them with its own Owen scrambling and its own
+/// shuffled sample order. Every pair of dimensions of a pixel's samples is a (0, 2)-sequence,
+/// stratified in every grid of as many cells as samples. Works best with a power of two
+/// samples per pixel.
+/// https://pbr-book.org/4ed/Sampling_and_Reconstruction/Sobol_Samplers
+/// Brent Burley, Practical Hash-based Owen Scrambling, https://jcgt.org/published/0009/04/01/
+#[derive(Debug, Clone)]
+pub struct Sobol {
+    samples_per_pixel: u32,
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl Sobol {
+    #[requires(samples_per_pixel > 0)]
+    pub fn new(samples_per_pixel: u32) -> Sobol {
+        Sobol {
+            samples_per_pixel,
+            seed: 0,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// Returns the sampler generating other values, e.g. for another frame.
+    pub fn with_seed(mut self, seed: u64) -> Sobol {
+        self.seed = seed;
+        self
+    }
+
+    /// The shuffled sample index and the scrambling seed of the next dimensions.
+    fn next_dimensions(&mut self, dimensions: u64) -> (u32, u64) {
+        let seed = hash(&[self.pixel, self.dimension, self.seed]);
+        let index = permute(self.index, self.samples_per_pixel, mix_bits(seed) as u32);
+        self.dimension += dimensions;
+        (index, seed)
+    }
+}
+
+/// Dimension 0 or 1 of the Sobol sequence as 32 bits of a fraction.
+fn sobol(index: u32, dimension: usize) -> u32 {
+    match dimension {
+        0 => index.reverse_bits(),
+        _ => (0..32)
+            .filter(|bit| (index >> bit) & 1 == 1)
+            .fold(0, |value, bit| value ^ SECOND_DIMENSION[bit]),
+    }
+}
+
+/// Owen scrambling, randomly flipping every bit depending on the bits above it.
+/// Keeps the stratification of the sequence while decorrelating differently seeded copies.
+fn owen_scramble(value: u32, seed: u32) -> u32 {
+    let mut v = value.reverse_bits();
+    v ^= v.wrapping_mul(0x3d20adea);
+    v = v.wrapping_add(seed);
+    v = v.wrapping_mul((seed >> 16) | 1);
+    v ^= v.wrapping_mul(0x05526c56);
+    v ^= v.wrapping_mul(0x53a22864);
+    v.reverse_bits()
+}
+
+fn to_unit(value: u32) -> f64 {
+    value as f64 / 2f64.powi(32)
+}
+
+impl Sampler for Sobol {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (index, seed) = self.next_dimensions(1);
+        to_unit(owen_scramble(sobol(index, 0), seed as u32))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (index, seed) = self.next_dimensions(2);
+        (
+            to_unit(owen_scramble(sobol(index, 0), seed as u32)),
+            to_unit(owen_scramble(sobol(index, 1), (seed >> 32) as u32)),
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sobol_sequence() {
+        let first = (0..4).map(|i| to_unit(sobol(i, 0))).collect::<Vec<_>>();
+        assert_eq!(first, vec![0.0, 0.5, 0.25, 0.75]);
+        let second = (0..4).map(|i| to_unit(sobol(i, 1))).collect::<Vec<_>>();
+        assert_eq!(second, vec![0.0, 0.5, 0.75, 0.25]);
+    }
+
+    #[test]
+    fn scrambling_keeps_strata() {
+        let mut halves = [0; 2];
+        for i in 0..2 {
+            halves[(to_unit(owen_scramble(sobol(i, 0), 12345)) * 2.0) as usize] += 1;
+        }
+        assert_eq!(halves, [1; 2]);
+        assert_ne!(owen_scramble(1 << 31, 1), owen_scramble(1 << 31, 2));
+    }
+
+    #[test]
+    fn elementary_intervals() {
+        // 16 samples have one sample in every cell of every grid with 16 cells
+        let mut sampler = Sobol::new(16);
+        let points = (0..16)
+            .map(|index| {
+                sampler.start_pixel_sample(3, index);
+                let pixel = sampler.get_2d();
+                let time = sampler.get_1d();
+                let lens = sampler.get_2d();
+                (pixel, time, lens)
+            })
+            .collect::<Vec<_>>();
+        for (columns, rows) in [(1, 16), (2, 8), (4, 4), (8, 2), (16, 1)] {
+            for pick in [
+                |p: &((f64, f64), f64, (f64, f64))| p.0,
+                |p: &((f64, f64), f64, (f64, f64))| p.2,
+            ] {
+                let mut cells = vec![0; 16];
+                for point in &points {
+                    let (x, y) = pick(point);
+                    let cell = (y * rows as f64) as usize * columns + (x * columns as f64) as usize;
+                    cells[cell] += 1;
+                }
+                assert_eq!(cells, vec![1; 16], "{}x{}", columns, rows);
+            }
+        }
+        let mut line = [0; 16];
+        for (_, time, _) in &points {
+            line[(time * 16.0) as usize] += 1;
+        }
+        assert_eq!(line, [1; 16]);
+
+        // Another pixel gets other points
+        sampler.start_pixel_sample(4, 0);
+        assert_ne!(sampler.get_2d(), points[0].0);
+    }
+}
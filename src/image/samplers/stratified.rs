@@ -0,0 +1,136 @@
+use contracts::*;
+
+use crate::image::samplers::{hash, mix_bits, permute, to_unit, Sampler};
+
+/// Divides every dimension into as many strata as there are samples per pixel,
+/// two dimensions together into a grid of `x_strata` by `y_strata`.
+/// Every sample of a pixel falls into another stratum, in an order shuffled per dimension
+/// so the strata of different dimensions don't correlate.
+/// https://pbr-book.org/4ed/Sampling_and_Reconstruction/Stratified_Sampler
+#[derive(Debug, Clone)]
+pub struct Stratified {
+    x_strata: u32,
+    y_strata: u32,
+    /// Whether samples are placed randomly within their stratum instead of at its center.
+    pub jitter: bool,
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl Stratified {
+    #[requires(x_strata > 0 && y_strata > 0)]
+    pub fn new(x_strata: u32, y_strata: u32) -> Stratified {
+        Stratified {
+            x_strata,
+            y_strata,
+            jitter: true,
+            seed: 0,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// Returns the sampler placing samples at the centers of their strata when `jitter` is false.
+    pub fn with_jitter(mut self, jitter: bool) -> Stratified {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Returns the sampler generating other values, e.g. for another frame.
+    pub fn with_seed(mut self, seed: u64) -> Stratified {
+        self.seed = seed;
+        self
+    }
+
+    /// The stratum of the current sample and the random bits for jittering within it.
+    fn next_stratum(&mut self, dimensions: u64) -> (u32, u64) {
+        let seed = hash(&[self.pixel, self.dimension, self.seed]);
+        let stratum = permute(self.index, self.samples_per_pixel(), seed as u32);
+        let jitter = hash(&[seed, self.index as u64]);
+        self.dimension += dimensions;
+        (stratum, jitter)
+    }
+
+    fn offset(&self, bits: u64) -> f64 {
+        if self.jitter {
+            to_unit(bits)
+        } else {
+            0.5
+        }
+    }
+}
+
+impl Sampler for Stratified {
+    fn samples_per_pixel(&self) -> u32 {
+        self.x_strata * self.y_strata
+    }
+
+    fn start_pixel_sample(&mut self, pixel: u64, index: u32) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (stratum, jitter) = self.next_stratum(1);
+        (stratum as f64 + self.offset(jitter)) / self.samples_per_pixel() as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (stratum, jitter) = self.next_stratum(2);
+        let x = stratum % self.x_strata;
+        let y = stratum / self.x_strata;
+        (
+            (x as f64 + self.offset(jitter)) / self.x_strata as f64,
+            (y as f64 + self.offset(mix_bits(jitter))) / self.y_strata as f64,
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_sample_per_stratum() {
+        let mut sampler = Stratified::new(4, 2);
+        assert_eq!(sampler.samples_per_pixel(), 8);
+        let mut grid = [[0; 4]; 2];
+        let mut line = [0; 8];
+        let mut lens = [[0; 4]; 2];
+        for index in 0..8 {
+            sampler.start_pixel_sample(5, index);
+            let (x, y) = sampler.get_2d();
+            grid[(y * 2.0) as usize][(x * 4.0) as usize] += 1;
+            line[(sampler.get_1d() * 8.0) as usize] += 1;
+            let (x, y) = sampler.get_2d();
+            lens[(y * 2.0) as usize][(x * 4.0) as usize] += 1;
+        }
+        assert_eq!(grid, [[1; 4]; 2]);
+        assert_eq!(line, [1; 8]);
+        assert_eq!(lens, [[1; 4]; 2]);
+    }
+
+    #[test]
+    fn centered_without_jitter() {
+        let mut sampler = Stratified::new(2, 2).with_jitter(false);
+        let mut points = (0..4)
+            .map(|index| {
+                sampler.start_pixel_sample(0, index);
+                sampler.get_2d()
+            })
+            .collect::<Vec<_>>();
+        points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(
+            points,
+            vec![(0.25, 0.25), (0.25, 0.75), (0.75, 0.25), (0.75, 0.75)]
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! Mapping the unbounded linear radiance of a render to displayable 8-bit colors.
+//! https://64.github.io/tonemapping/
+
+use contracts::*;
+
+use crate::color::Color;
+use crate::vec3::Vec3;
+
+/// How radiance is compressed into the displayable range of 0.0 to 1.0, per channel.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Operator {
+    /// Cuts off everything above 1.0.
+    #[default]
+    Clamp,
+    /// `x / (1 + x)`, never reaching white.
+    Reinhard,
+    /// Reinhard reaching white at the radiance `white`.
+    ExtendedReinhard { white: f64 },
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve.
+    /// https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve/
+    Aces,
+    /// John Hable's filmic curve from Uncharted 2.
+    /// http://filmicworlds.com/blog/filmic-tonemapping-operators/
+    Hable,
+}
+
+impl Operator {
+    /// Applies the operator to one channel.
+    /// NaN is mapped to black and infinite radiance to white.
+    pub fn apply(self, x: f64) -> f64 {
+        // Also replaces NaN
+        let x = x.max(0.0);
+        if x == f64::INFINITY {
+            // The curves would divide infinity by infinity
+            return 1.0;
+        }
+        let y = match self {
+            Operator::Clamp => x,
+            Operator::Reinhard => x / (1.0 + x),
+            Operator::ExtendedReinhard { white } => x * (1.0 + x / (white * white)) / (1.0 + x),
+            Operator::Aces => {
+                // The fit expects the radiance pre-exposed by 0.6
+                let x = x * 0.6;
+                (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)
+            }
+            Operator::Hable => {
+                const EXPOSURE_BIAS: f64 = 2.0;
+                const WHITE: f64 = 11.2;
+                hable(x * EXPOSURE_BIAS) / hable(WHITE)
+            }
+        };
+        y.clamp(0.0, 1.0)
+    }
+}
+
+fn hable(x: f64) -> f64 {
+    const A: f64 = 0.15;
+    const B: f64 = 0.50;
+    const C: f64 = 0.10;
+    const D: f64 = 0.20;
+    const E: f64 = 0.02;
+    const F: f64 = 0.30;
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+/// How the tone mapped values are encoded for storage and display.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum Encoding {
+    /// Stored as is, for viewers that expect linear values.
+    Linear,
+    /// The sRGB transfer function, what displays and most image viewers expect.
+    /// https://en.wikipedia.org/wiki/SRGB#Transfer_function_(%22gamma%22)
+    #[default]
+    Srgb,
+    /// A pure power law with the given gamma, e.g. 2.2.
+    Gamma(f64),
+}
+
+impl Encoding {
+    /// Encodes one channel between 0.0 and 1.0.
+    #[requires((0.0..=1.0).contains(&x))]
+    pub fn encode(self, x: f64) -> f64 {
+        match self {
+            Encoding::Linear => x,
+            Encoding::Srgb => {
+                if x <= 0.0031308 {
+                    12.92 * x
+                } else {
+                    1.055 * x.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            Encoding::Gamma(gamma) => x.powf(1.0 / gamma),
+        }
+    }
+}
+
+/// Quantizes the radiance of a framebuffer into colors:
+/// exposure, then the tone mapping operator, then the output encoding.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct ToneMapper {
+    /// Exposure compensation in stops, every stop doubles the brightness.
+    pub exposure: f64,
+    pub operator: Operator,
+    pub encoding: Encoding,
+}
+
+impl ToneMapper {
+    pub fn new(operator: Operator) -> ToneMapper {
+        ToneMapper {
+            operator,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the tone mapper brightening by `exposure` stops.
+    pub fn with_exposure(mut self, exposure: f64) -> ToneMapper {
+        self.exposure = exposure;
+        self
+    }
+
+    /// Returns the tone mapper encoding its output by `encoding`.
+    pub fn with_encoding(mut self, encoding: Encoding) -> ToneMapper {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Maps one channel of radiance to a displayable value between 0.0 and 1.0.
+    pub fn map_channel(&self, x: f64) -> f64 {
+        let exposed = x * 2f64.powf(self.exposure);
+        self.encoding.encode(self.operator.apply(exposed))
+    }
+
+    /// The color displaying `radiance`.
+    pub fn map(&self, radiance: Vec3) -> Color {
+        let to_u8 = |x: f64| (self.map_channel(x) * 255.0).round() as u8;
+        Color {
+            r: to_u8(radiance.x),
+            g: to_u8(radiance.y),
+            b: to_u8(radiance.z),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPERATORS: [Operator; 5] = [
+        Operator::Clamp,
+        Operator::Reinhard,
+        Operator::ExtendedReinhard { white: 4.0 },
+        Operator::Aces,
+        Operator::Hable,
+    ];
+
+    #[test]
+    fn clamp() {
+        let mapper = ToneMapper::default().with_encoding(Encoding::Linear);
+        assert_eq!(
+            mapper.map(Vec3::new(0.0, 0.5, 1.0)),
+            Color::from(Vec3::new(0.0, 0.5, 1.0))
+        );
+        assert_eq!(
+            mapper.map(Vec3::new(-1.0, 2.0, 100.0)),
+            Color {
+                r: 0,
+                g: 255,
+                b: 255
+            }
+        );
+    }
+
+    #[test]
+    fn operators_are_monotonic_and_bounded() {
+        for operator in OPERATORS {
+            assert_eq!(operator.apply(0.0), 0.0, "{:?}", operator);
+            assert_eq!(operator.apply(-1.0), 0.0, "{:?}", operator);
+            let mut last = 0.0;
+            for i in 1..1000 {
+                let y = operator.apply(i as f64 * 0.02);
+                assert!((0.0..=1.0).contains(&y), "{:?}", operator);
+                assert!(y >= last, "{:?}", operator);
+                last = y;
+            }
+        }
+    }
+
+    #[test]
+    fn operator_curves() {
+        assert_eq!(Operator::Reinhard.apply(1.0), 0.5);
+        assert_eq!(Operator::ExtendedReinhard { white: 4.0 }.apply(4.0), 1.0);
+        assert!(
+            Operator::ExtendedReinhard { white: 4.0 }.apply(1.0) > Operator::Reinhard.apply(1.0)
+        );
+        assert!((Operator::Hable.apply(11.2 / 2.0) - 1.0).abs() < 1e-12);
+        assert!(Operator::Aces.apply(100.0) > 0.99);
+        // Filmic curves have a toe, darkening shadows
+        assert!(Operator::Hable.apply(0.01) < 0.01);
+    }
+
+    #[test]
+    fn srgb_encoding() {
+        assert_eq!(Encoding::Srgb.encode(0.0), 0.0);
+        assert!((Encoding::Srgb.encode(1.0) - 1.0).abs() < 1e-12);
+        // Linear segment near black
+        assert!((Encoding::Srgb.encode(0.001) - 0.01292).abs() < 1e-12);
+        // Mid grey is brightened to about 0.735
+        assert!((Encoding::Srgb.encode(0.5) - 0.7354).abs() < 1e-4);
+        assert!((Encoding::Gamma(2.0).encode(0.25) - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn exposure() {
+        let mapper = ToneMapper::default()
+            .with_encoding(Encoding::Linear)
+            .with_exposure(1.0);
+        assert_eq!(mapper.map_channel(0.25), 0.5);
+        assert_eq!(mapper.with_exposure(-2.0).map_channel(2.0), 0.5);
+    }
+
+    #[test]
+    fn non_finite_radiance() {
+        for operator in OPERATORS {
+            let mapper = ToneMapper::new(operator).with_encoding(Encoding::Linear);
+            assert_eq!(mapper.map_channel(f64::INFINITY), 1.0, "{:?}", operator);
+            assert_eq!(mapper.map_channel(f64::NEG_INFINITY), 0.0, "{:?}", operator);
+            assert_eq!(mapper.map_channel(f64::NAN), 0.0, "{:?}", operator);
+        }
+    }
+}
@@ -0,0 +1,633 @@
+//! Light transport, estimating the radiance arriving along camera rays.
+
+use contracts::*;
+
+use crate::image::samplers::Sampler;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::scene::Scene;
+use crate::vec3::{Pnt3, UnitVec3, Vec3};
+
+/// Distance new rays start away from the surface they leave, to not hit it again.
+const RAY_EPSILON: f64 = 0.0001;
+/// Fraction of the distance to a light that shadow rays may fall short by,
+/// so the light's own surface doesn't shadow it.
+const SHADOW_EPSILON: f64 = 1e-6;
+/// Highest probability a path survives Russian roulette with.
+/// Keeps paths through highly reflective surfaces from bouncing forever.
+const MAX_SURVIVAL_PROBABILITY: f64 = 0.95;
+
+/// A unidirectional path tracer.
+/// https://pbr-book.org/3ed-2018/Light_Transport_I_Surface_Reflection/Path_Tracing
+///
+/// Every bounce samples the BSDF of the hit surface and scales the path throughput
+/// by its weight. Emissive objects and analytic lights are sampled explicitly at every bounce
+/// (next event estimation), with shadow rays testing their visibility. After `min_bounces`,
+/// paths are terminated by Russian roulette with a probability based on their throughput,
+/// which keeps the estimate unbiased.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathTracer {
+    /// Bounces before Russian roulette may terminate a path.
+    pub min_bounces: u32,
+    /// Hard limit on the number of bounces.
+    pub max_bounces: u32,
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        PathTracer {
+            min_bounces: 3,
+            max_bounces: 64,
+        }
+    }
+}
+
+impl PathTracer {
+    #[requires(min_bounces <= max_bounces)]
+    pub fn new(min_bounces: u32, max_bounces: u32) -> PathTracer {
+        PathTracer {
+            min_bounces,
+            max_bounces,
+        }
+    }
+
+    /// Estimates the radiance arriving at the ray origin from the ray direction,
+    /// drawing every bounce's light and BSDF samples as pairs of dimensions from `sampler`.
+    pub fn radiance(&self, scene: &Scene, ray: &Ray, sampler: &mut dyn Sampler) -> Vec3 {
+        let mut radiance = Vec3::null();
+        let mut throughput = Vec3::new(1.0, 1.0, 1.0);
+        let mut ray = ray.clone();
+        let mut specular_bounce = false;
+
+        for bounce in 0..=self.max_bounces {
+            let hit = match scene.intersect(&ray) {
+                Some(hit) => hit,
+                None => {
+                    radiance = radiance + throughput * scene.background();
+                    break;
+                }
+            };
+            let hit_point = ray.at(hit.t);
+            let footprint = ray.footprint(hit.t);
+            let material = hit
+                .material
+                .at(hit.uv, hit_point, hit.uv_footprint(footprint));
+            let wo = -ray.dir;
+            let front_face = hit.normal.dot(wo) >= 0.0;
+            let geometric = if front_face { hit.normal } else { -hit.normal };
+            let shading = material.shading_normal(&hit, hit_point);
+            let shading = if front_face { shading } else { -shading };
+            // A perturbed normal facing away from the viewer leaves nothing to scatter into
+            let normal = if shading.dot(wo) > 0.0 {
+                shading
+            } else {
+                geometric
+            };
+            // Leaving a material, the light was absorbed on its way through
+            if !front_face {
+                throughput = throughput * material.transmittance(hit.t);
+            }
+
+            // Light hit after a diffuse bounce was already accounted for by sampling it
+            if bounce == 0 || specular_bounce {
+                radiance = radiance + throughput * material.emission;
+            }
+            if bounce == self.max_bounces {
+                break;
+            }
+
+            if !material.is_specular() {
+                let direct = self
+                    .sample_emitter(scene, hit_point, normal, &ray, &material, sampler)
+                    + self.sample_lights(scene, hit_point, normal, &ray, &material);
+                radiance = radiance + throughput * direct;
+            }
+
+            let sample = match material.sample(normal, wo, front_face, sampler.get_2d()) {
+                Some(sample) => sample,
+                None => break,
+            };
+            throughput = throughput * sample.weight;
+            specular_bounce = sample.specular;
+
+            if bounce >= self.min_bounces {
+                let survival = throughput.max_component().min(MAX_SURVIVAL_PROBABILITY);
+                if sampler.get_1d() >= survival {
+                    break;
+                }
+                throughput = throughput / survival;
+            }
+
+            let offset = if sample.wi.dot(geometric) >= 0.0 {
+                geometric * RAY_EPSILON
+            } else {
+                geometric * -RAY_EPSILON
+            };
+            // The cone keeps its spread, exact for mirrors and a lower bound for rough surfaces
+            ray = Ray::new(hit_point + offset, sample.wi)
+                .with_time(ray.time)
+                .with_cone(footprint, ray.spread);
+        }
+        radiance
+    }
+
+    /// Radiance scattered back along `ray`, arriving at `point`,
+    /// from a point sampled on a randomly picked emitter.
+    fn sample_emitter(
+        &self,
+        scene: &Scene,
+        point: Pnt3,
+        normal: UnitVec3,
+        ray: &Ray,
+        material: &Material,
+        sampler: &mut dyn Sampler,
+    ) -> Vec3 {
+        let wo = -ray.dir;
+        let emitters = scene.emitters();
+        if emitters.is_empty() {
+            return Vec3::null();
+        }
+        let pick = ((sampler.get_1d() * emitters.len() as f64) as usize).min(emitters.len() - 1);
+        let light = &scene.objects()[emitters[pick]];
+        let sample = match light.sample_surface(sampler.get_2d(), ray.time) {
+            Some(sample) => sample,
+            None => return Vec3::null(),
+        };
+
+        let origin = point + normal * RAY_EPSILON;
+        let to_light = sample.point - origin;
+        let distance = to_light.len();
+        let wi = match to_light.normalize() {
+            Ok(wi) => wi,
+            Err(_) => return Vec3::null(),
+        };
+        let bsdf = material.eval(normal, wo, wi);
+        let cos_light = sample.normal.dot(wi).abs();
+        if bsdf == Vec3::null() || cos_light <= 0.0 {
+            return Vec3::null();
+        }
+        if Self::occluded(scene, &Ray::new(origin, wi).with_time(ray.time), distance) {
+            return Vec3::null();
+        }
+
+        // Convert the pdf per area of the light to per solid angle at the point
+        let pdf = sample.pdf * distance * distance / cos_light / emitters.len() as f64;
+        bsdf * light.material().at(sample.uv, sample.point, 0.0).emission / pdf
+    }
+
+    /// Radiance scattered back along `ray`, arriving at `point`,
+    /// from all analytic lights of the scene.
+    fn sample_lights(
+        &self,
+        scene: &Scene,
+        point: Pnt3,
+        normal: UnitVec3,
+        ray: &Ray,
+        material: &Material,
+    ) -> Vec3 {
+        let wo = -ray.dir;
+        let origin = point + normal * RAY_EPSILON;
+        scene
+            .lights()
+            .iter()
+            .filter_map(|light| light.sample(origin))
+            .filter(|sample| {
+                let shadow = Ray::new(origin, sample.wi).with_time(ray.time);
+                !Self::occluded(scene, &shadow, sample.distance)
+            })
+            .fold(Vec3::null(), |acc, sample| {
+                acc + material.eval(normal, wo, sample.wi) * sample.irradiance
+            })
+    }
+
+    /// Whether anything blocks the way along the shadow ray for `distance`.
+    fn occluded(scene: &Scene, ray: &Ray, distance: f64) -> bool {
+        scene
+            .intersect(ray)
+            .is_some_and(|blocker| blocker.t < distance * (1.0 - SHADOW_EPSILON))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::image::samplers::Independent;
+    use crate::light::{DirectionalLight, PointLight};
+    use crate::scene::moving::{Motion, Moving};
+    use crate::scene::plane::Plane;
+    use crate::scene::quad::Quad;
+    use crate::scene::sphere::Sphere;
+    use crate::texture::{Checkerboard, Filter, ImageTexture, TextureRef};
+
+    /// Independent random values, continuing over every path traced with the sampler.
+    fn sampler() -> Independent {
+        let mut sampler = Independent::new(1).with_seed(42);
+        sampler.start_pixel_sample(0, 0);
+        sampler
+    }
+
+    fn grey_sphere_scene() -> Scene {
+        let mut scene = Scene::new();
+        scene.add(
+            Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 1.0).with_material(Material {
+                color: Vec3::new(255.0, 255.0, 255.0),
+                albedo: 0.5,
+                ..Default::default()
+            }),
+        );
+        scene.build_bvh();
+        scene
+    }
+
+    #[test]
+    fn missing_rays_see_the_background() {
+        let mut scene = grey_sphere_scene();
+        scene.set_background(Vec3::new(0.25, 0.5, 1.0));
+        let ray = Ray::new(Pnt3::new(0.0, 5.0, -5.0), UnitVec3::new(0.0, 0.0, 1.0));
+        let radiance = PathTracer::default().radiance(&scene, &ray, &mut sampler());
+        assert_eq!(radiance, Vec3::new(0.25, 0.5, 1.0));
+    }
+
+    #[test]
+    fn convex_object_under_uniform_sky() {
+        // Every bounce off a convex object escapes, so the radiance is exactly the reflectance
+        let scene = grey_sphere_scene();
+        let ray = Ray::new(Pnt3::new(0.0, 0.0, -5.0), UnitVec3::new(0.0, 0.0, 1.0));
+        let mut sampler = sampler();
+        for _ in 0..100 {
+            let radiance = PathTracer::default().radiance(&scene, &ray, &mut sampler);
+            assert!((radiance - Vec3::new(0.5, 0.5, 0.5)).len() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn russian_roulette_is_unbiased() {
+        let scene = grey_sphere_scene();
+        let ray = Ray::new(Pnt3::new(0.0, 0.0, -5.0), UnitVec3::new(0.0, 0.0, 1.0));
+        let integrator = PathTracer::new(0, 64);
+        let mut sampler = sampler();
+        let n = 20000;
+        let mean = (0..n)
+            .map(|_| integrator.radiance(&scene, &ray, &mut sampler).x)
+            .sum::<f64>()
+            / n as f64;
+        assert!((mean - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn bounces_are_limited() {
+        // A closed box without any light source stays black
+        let mut scene = Scene::new();
+        scene.add(crate::scene::cube::Cube::new(
+            Pnt3::new(-1.0, -1.0, -1.0),
+            Pnt3::new(1.0, 1.0, 1.0),
+        ));
+        let ray = Ray::new(Pnt3::new(0.0, 0.0, 0.0), UnitVec3::new(0.0, 0.0, 1.0));
+        let radiance = PathTracer::new(8, 8).radiance(&scene, &ray, &mut sampler());
+        assert_eq!(radiance, Vec3::null());
+    }
+
+    #[test]
+    fn emitters_are_seen_directly() {
+        let mut scene = Scene::new();
+        scene.set_background(Vec3::null());
+        scene.add(
+            Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 1.0).with_material(Material {
+                emission: Vec3::new(2.0, 3.0, 4.0),
+                albedo: 0.0,
+                ..Default::default()
+            }),
+        );
+        let ray = Ray::new(Pnt3::new(0.0, 0.0, -5.0), UnitVec3::new(0.0, 0.0, 1.0));
+        let radiance = PathTracer::default().radiance(&scene, &ray, &mut sampler());
+        assert_eq!(radiance, Vec3::new(2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn textures_are_looked_up_at_the_hit() {
+        let mut scene = Scene::new();
+        scene.set_background(Vec3::null());
+        scene.add(
+            Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 1.0).with_material(Material {
+                emission_texture: Some(TextureRef::new(Checkerboard::new(
+                    Vec3::new(255.0, 255.0, 255.0),
+                    Vec3::new(0.0, 0.0, 255.0),
+                    2.0,
+                ))),
+                albedo: 0.0,
+                ..Default::default()
+            }),
+        );
+        let tracer = PathTracer::default();
+        let radiance = |origin: Pnt3, dir: UnitVec3| {
+            tracer.radiance(&scene, &Ray::new(origin, dir), &mut sampler())
+        };
+        let front = radiance(Pnt3::new(0.0, 0.0, -5.0), UnitVec3::new(0.0, 0.0, 1.0));
+        let side = radiance(Pnt3::new(-5.0, 0.0, 0.0), UnitVec3::new(1.0, 0.0, 0.0));
+        assert_eq!(front, Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(side, Vec3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn distant_textures_are_filtered() {
+        // A black and white image texture on a unit square, five units away
+        let checker = ImageTexture::from_colors(
+            2,
+            2,
+            &[
+                Color::black(),
+                Color::white(),
+                Color::white(),
+                Color::black(),
+            ],
+        )
+        .with_filter(Filter::Nearest);
+        let mut scene = Scene::new();
+        scene.set_background(Vec3::null());
+        scene.add(
+            Quad::new(
+                Pnt3::new(-0.5, -0.5, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            )
+            .with_material(Material {
+                albedo: 0.0,
+                emission_texture: Some(TextureRef::new(checker)),
+                ..Default::default()
+            }),
+        );
+        let ray = Ray::new(Pnt3::new(0.25, 0.25, 5.0), UnitVec3::new(0.0, 0.0, -1.0));
+        let tracer = PathTracer::default();
+        let sharp = tracer.radiance(&scene, &ray, &mut sampler());
+        assert_eq!(sharp, Vec3::new(1.0, 1.0, 1.0));
+        // A cone wider than the square only sees the average
+        let blurred = tracer.radiance(&scene, &ray.with_cone(0.0, 0.5), &mut sampler());
+        assert_eq!(blurred, Vec3::new(0.5, 0.5, 0.5));
+    }
+
+    /// A grey floor lit by a small square light one unit above, in an otherwise black scene.
+    fn lit_floor(blocked: bool) -> Scene {
+        floor_under(
+            Material {
+                albedo: 0.0,
+                emission: Vec3::new(100.0, 100.0, 100.0),
+                ..Default::default()
+            },
+            blocked,
+        )
+    }
+
+    /// A grey floor lit by a small square of the `light` material one unit above.
+    fn floor_under(light: Material, blocked: bool) -> Scene {
+        let mut scene = Scene::new();
+        scene.set_background(Vec3::null());
+        scene.add(
+            Plane::new(Pnt3::new(0.0, 0.0, 0.0), UnitVec3::new(0.0, 1.0, 0.0)).with_material(
+                Material {
+                    albedo: 0.5,
+                    ..Default::default()
+                },
+            ),
+        );
+        scene.add(
+            Quad::new(
+                Pnt3::new(-0.05, 1.0, -0.05),
+                Vec3::new(0.1, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 0.1),
+            )
+            .with_material(light),
+        );
+        if blocked {
+            scene.add(Quad::new(
+                Pnt3::new(-1.0, 0.5, -1.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 2.0),
+            ));
+        }
+        scene.build_bvh();
+        scene
+    }
+
+    #[test]
+    fn area_lights_are_sampled() {
+        // Irradiance below the light is close to `emission * area / distance²` = 1.0,
+        // the diffuse floor reflects `albedo / PI` of it
+        let scene = lit_floor(false);
+        let ray = Ray::new(Pnt3::new(0.0, 0.5, -0.5), UnitVec3::new(0.0, -1.0, 1.0));
+        let mut sampler = sampler();
+        let n = 2000;
+        let mean = (0..n)
+            .map(|_| PathTracer::default().radiance(&scene, &ray, &mut sampler).x)
+            .sum::<f64>()
+            / n as f64;
+        assert!((mean - 0.5 / std::f64::consts::PI).abs() < 0.005);
+    }
+
+    #[test]
+    fn textured_area_lights_are_sampled() {
+        // Half of the light is twice as bright as the untextured one, the other half dark
+        let checkerboard = TextureRef::new(Checkerboard::new(
+            Vec3::new(200.0, 200.0, 200.0) * 255.0,
+            Vec3::null(),
+            2.0,
+        ));
+        for emission in [Vec3::null(), Vec3::new(1000.0, 1000.0, 1000.0)] {
+            let scene = floor_under(
+                Material {
+                    albedo: 0.0,
+                    emission,
+                    emission_texture: Some(checkerboard.clone()),
+                    ..Default::default()
+                },
+                false,
+            );
+            assert_eq!(scene.emitters(), &[1]);
+            let ray = Ray::new(Pnt3::new(0.0, 0.5, -0.5), UnitVec3::new(0.0, -1.0, 1.0));
+            let mut sampler = sampler();
+            let n = 10000;
+            let mean = (0..n)
+                .map(|_| PathTracer::default().radiance(&scene, &ray, &mut sampler).x)
+                .sum::<f64>()
+                / n as f64;
+            assert!((mean - 0.5 / std::f64::consts::PI).abs() < 0.005);
+        }
+    }
+
+    /// Records which dimensions the integrator draws, all at the center of their range.
+    #[derive(Clone, Default)]
+    struct Recording {
+        draws: Vec<&'static str>,
+    }
+
+    impl Sampler for Recording {
+        fn samples_per_pixel(&self) -> u32 {
+            1
+        }
+
+        fn start_pixel_sample(&mut self, _pixel: u64, _index: u32) {
+            self.draws.clear();
+        }
+
+        fn get_1d(&mut self) -> f64 {
+            self.draws.push("1d");
+            0.5
+        }
+
+        fn get_2d(&mut self) -> (f64, f64) {
+            self.draws.push("2d");
+            (0.5, 0.5)
+        }
+
+        fn clone_box(&self) -> Box<dyn Sampler> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn light_and_bsdf_samples_are_pairs() {
+        // Picking the light, the point on it, then the bounce direction straight up
+        let scene = lit_floor(false);
+        let ray = Ray::new(Pnt3::new(0.0, 0.5, -0.5), UnitVec3::new(0.0, -1.0, 1.0));
+        let mut sampler = Recording::default();
+        PathTracer::new(1, 1).radiance(&scene, &ray, &mut sampler);
+        assert_eq!(sampler.draws, ["1d", "2d", "2d"]);
+    }
+
+    #[test]
+    fn shadow_rays_are_blocked() {
+        let scene = lit_floor(true);
+        let ray = Ray::new(Pnt3::new(0.0, 0.25, -0.25), UnitVec3::new(0.0, -1.0, 1.0));
+        // Only direct light, the floor around the blocker is lit and bounces light below it
+        let integrator = PathTracer::new(1, 1);
+        let mut sampler = sampler();
+        for _ in 0..100 {
+            let radiance = integrator.radiance(&scene, &ray, &mut sampler);
+            assert_eq!(radiance, Vec3::null());
+        }
+    }
+
+    /// A grey floor in an otherwise black scene.
+    fn dark_floor() -> Scene {
+        let mut scene = Scene::new();
+        scene.set_background(Vec3::null());
+        scene.add(
+            Plane::new(Pnt3::new(0.0, 0.0, 0.0), UnitVec3::new(0.0, 1.0, 0.0)).with_material(
+                Material {
+                    albedo: 0.5,
+                    ..Default::default()
+                },
+            ),
+        );
+        scene
+    }
+
+    #[test]
+    fn moving_emitters_are_sampled() {
+        // A small bright sphere passing one unit above the floor's origin at time 1.0
+        let mut scene = dark_floor();
+        scene.add(Moving::new(
+            Sphere::new(Pnt3::new(-4.0, 1.0, 0.0), 0.1).with_material(Material {
+                albedo: 0.0,
+                emission: Vec3::new(100.0, 100.0, 100.0),
+                ..Default::default()
+            }),
+            Motion::Linear(Vec3::new(4.0, 0.0, 0.0)),
+        ));
+        assert_eq!(scene.emitters(), &[1]);
+        let mean = |time: f64| {
+            let ray =
+                Ray::new(Pnt3::new(0.0, 0.5, -0.5), UnitVec3::new(0.0, -1.0, 1.0)).with_time(time);
+            let mut sampler = sampler();
+            let n = 4000;
+            (0..n)
+                .map(|_| PathTracer::default().radiance(&scene, &ray, &mut sampler).x)
+                .sum::<f64>()
+                / n as f64
+        };
+        // Irradiance is `emission * PI * (r / distance)²`, the floor reflects `albedo / PI`
+        assert!((mean(1.0) - 0.5).abs() < 0.05);
+        assert!(mean(0.0) < 0.05);
+    }
+
+    #[test]
+    fn point_lights() {
+        let mut scene = dark_floor();
+        scene.add_light(PointLight::new(
+            Pnt3::new(0.0, 2.0, 0.0),
+            Vec3::new(4.0, 4.0, 4.0),
+        ));
+        let ray = Ray::new(Pnt3::new(0.0, 0.5, -0.5), UnitVec3::new(0.0, -1.0, 1.0));
+        let radiance = PathTracer::default().radiance(&scene, &ray, &mut sampler());
+        assert!((radiance.x - 0.5 / std::f64::consts::PI).abs() < 1e-4);
+    }
+
+    #[test]
+    fn directional_lights_cast_shadows() {
+        let mut scene = dark_floor();
+        // Sun at 60° from the zenith
+        scene.add_light(DirectionalLight::new(
+            UnitVec3::new(0.0, -1.0, 3.0f64.sqrt()),
+            Vec3::new(1.0, 1.0, 1.0),
+        ));
+        let ray = Ray::new(Pnt3::new(0.0, 0.5, -0.5), UnitVec3::new(0.0, -1.0, 1.0));
+        let radiance = PathTracer::default().radiance(&scene, &ray, &mut sampler());
+        assert!((radiance.x - 0.5 * 0.5 / std::f64::consts::PI).abs() < 1e-9);
+
+        // Blocks the sun, but not the camera ray
+        scene.add(Quad::new(
+            Pnt3::new(-1.0, 0.25, -1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 0.65),
+        ));
+        let radiance = PathTracer::new(1, 1).radiance(&scene, &ray, &mut sampler());
+        assert_eq!(radiance, Vec3::null());
+    }
+
+    #[test]
+    fn clear_glass_under_uniform_sky() {
+        // Glass neither absorbs nor emits, every path sees the background
+        let mut scene = Scene::new();
+        scene.add(
+            Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 1.0).with_material(Material::dielectric(1.5)),
+        );
+        let integrator = PathTracer::default();
+        let mut sampler = sampler();
+        let n = 2000;
+        let mean = (0..n)
+            .map(|i| {
+                let ray = Ray::new(
+                    Pnt3::new((i % 10) as f64 * 0.1 - 0.5, 0.0, -5.0),
+                    UnitVec3::new(0.0, 0.0, 1.0),
+                );
+                integrator.radiance(&scene, &ray, &mut sampler).x
+            })
+            .sum::<f64>()
+            / n as f64;
+        assert!((mean - 1.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn tinted_glass_absorbs() {
+        // Straight through the middle of a slab, at normal incidence 4% are reflected per interface
+        let mut scene = Scene::new();
+        scene.add(
+            crate::scene::cube::Cube::new(Pnt3::new(-5.0, -5.0, 0.0), Pnt3::new(5.0, 5.0, 1.0))
+                .with_material(Material {
+                    color: Vec3::new(255.0, 127.5, 127.5),
+                    absorption_coefficient: 1.0,
+                    ..Material::dielectric(1.5)
+                }),
+        );
+        let ray = Ray::new(Pnt3::new(0.0, 0.0, -5.0), UnitVec3::new(0.0, 0.0, 1.0));
+        let integrator = PathTracer::default();
+        let mut sampler = sampler();
+        let n = 2000;
+        let mean = (0..n)
+            .map(|_| integrator.radiance(&scene, &ray, &mut sampler))
+            .fold(Vec3::null(), |acc, radiance| acc + radiance)
+            / n as f64;
+        assert!(mean.y < 0.6 && mean.y > 0.4);
+        assert!(mean.x > 0.95);
+    }
+}
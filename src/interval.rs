@@ -1,5 +1,3 @@
-use contracts::*;
-
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Interval<T> {
     pub left: T,
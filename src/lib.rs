@@ -0,0 +1,15 @@
+pub mod aabb;
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod image;
+pub mod integrator;
+pub mod interval;
+pub mod light;
+pub mod material;
+pub mod obj;
+pub mod ray;
+pub mod sampling;
+pub mod scene;
+pub mod texture;
+pub mod vec3;
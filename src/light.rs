@@ -0,0 +1,201 @@
+//! Analytic light sources without a surface, described by a position or direction alone.
+//! Unlike emissive objects they can't be hit by rays and are only found by sampling them.
+
+use contracts::*;
+
+use crate::vec3::{Pnt3, UnitVec3, Vec3};
+
+/// A light source that illuminates points from a single direction.
+/// Implement this for custom lights to add them to a [`Scene`](crate::scene::Scene).
+pub trait Light: Send + Sync {
+    /// The light arriving at `point`, if any.
+    fn sample(&self, point: Pnt3) -> Option<LightSample>;
+}
+
+/// Light arriving at a point from a [`Light`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct LightSample {
+    /// Direction towards the light.
+    pub wi: UnitVec3,
+    /// Distance to the light, [`f64::INFINITY`] for directional lights.
+    pub distance: f64,
+    /// Irradiance arriving at the point, on a surface facing the light.
+    pub irradiance: Vec3,
+}
+
+/// Light emitted equally in all directions from a single point.
+/// https://pbr-book.org/3ed-2018/Light_Sources/Point_Lights
+#[derive(Debug, PartialEq, Clone)]
+pub struct PointLight {
+    pub position: Pnt3,
+    /// Radiant intensity, the irradiance at a distance of one unit.
+    pub intensity: Vec3,
+}
+
+impl PointLight {
+    pub fn new(position: Pnt3, intensity: Vec3) -> PointLight {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, point: Pnt3) -> Option<LightSample> {
+        sample_position(self.position, self.intensity, point)
+    }
+}
+
+/// A point light restricted to a cone around `direction`.
+/// Full intensity within `inner_angle`, falling off smoothly to zero at `outer_angle`.
+/// https://pbr-book.org/3ed-2018/Light_Sources/Point_Lights#Spotlights
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpotLight {
+    pub position: Pnt3,
+    /// Direction the cone points to.
+    pub direction: UnitVec3,
+    /// Radiant intensity along the cone, the irradiance at a distance of one unit.
+    pub intensity: Vec3,
+    /// Half angle of the fully lit cone, in radians.
+    pub inner_angle: f64,
+    /// Half angle at which the light is cut off, in radians.
+    pub outer_angle: f64,
+}
+
+impl SpotLight {
+    #[requires(0.0 <= inner_angle && inner_angle <= outer_angle)]
+    #[requires(outer_angle <= std::f64::consts::PI)]
+    pub fn new(
+        position: Pnt3,
+        direction: UnitVec3,
+        intensity: Vec3,
+        inner_angle: f64,
+        outer_angle: f64,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            direction,
+            intensity,
+            inner_angle,
+            outer_angle,
+        }
+    }
+
+    /// Fraction of the intensity emitted towards `dir`.
+    fn falloff(&self, dir: UnitVec3) -> f64 {
+        let cos_theta = self.direction.dot(dir);
+        let cos_inner = self.inner_angle.cos();
+        let cos_outer = self.outer_angle.cos();
+        if cos_theta >= cos_inner {
+            return 1.0;
+        }
+        if cos_theta <= cos_outer {
+            return 0.0;
+        }
+        let x = (cos_theta - cos_outer) / (cos_inner - cos_outer);
+        x * x * (3.0 - 2.0 * x)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: Pnt3) -> Option<LightSample> {
+        let sample = sample_position(self.position, self.intensity, point)?;
+        let falloff = self.falloff(-sample.wi);
+        (falloff > 0.0).then(|| LightSample {
+            irradiance: sample.irradiance * falloff,
+            ..sample
+        })
+    }
+}
+
+/// Parallel light from an infinitely distant source, like the sun.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DirectionalLight {
+    /// Direction the light travels in.
+    pub direction: UnitVec3,
+    /// Irradiance on surfaces facing the light.
+    pub irradiance: Vec3,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: UnitVec3, irradiance: Vec3) -> DirectionalLight {
+        DirectionalLight {
+            direction,
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: Pnt3) -> Option<LightSample> {
+        Some(LightSample {
+            wi: -self.direction,
+            distance: f64::INFINITY,
+            irradiance: self.irradiance,
+        })
+    }
+}
+
+/// Light from `intensity` at `position` arriving at `point`, falling off with the inverse square.
+fn sample_position(position: Pnt3, intensity: Vec3, point: Pnt3) -> Option<LightSample> {
+    let to_light = position - point;
+    let distance = to_light.len();
+    Some(LightSample {
+        wi: to_light.normalize().ok()?,
+        distance,
+        irradiance: intensity / (distance * distance),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_4;
+
+    #[test]
+    fn point_light_falloff() {
+        let light = PointLight::new(Pnt3::new(0.0, 2.0, 0.0), Vec3::new(4.0, 8.0, 12.0));
+        let sample = light.sample(Pnt3::new(0.0, 0.0, 0.0)).unwrap();
+        assert_eq!(sample.wi, UnitVec3::new(0.0, 1.0, 0.0));
+        assert_eq!(sample.distance, 2.0);
+        assert_eq!(sample.irradiance, Vec3::new(1.0, 2.0, 3.0));
+
+        // Points at the light's position receive nothing
+        assert!(light.sample(Pnt3::new(0.0, 2.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn spot_light_cone() {
+        let light = SpotLight::new(
+            Pnt3::new(0.0, 1.0, 0.0),
+            UnitVec3::new(0.0, -1.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            FRAC_PI_4 / 2.0,
+            FRAC_PI_4,
+        );
+        let inside = light.sample(Pnt3::new(0.0, 0.0, 0.0)).unwrap();
+        assert_eq!(inside.irradiance, Vec3::new(1.0, 1.0, 1.0));
+
+        // 30° off the axis, between the inner and outer angle
+        let edge = light
+            .sample(Pnt3::new((30.0f64).to_radians().tan(), 0.0, 0.0))
+            .unwrap();
+        assert!(edge.irradiance.x > 0.0 && edge.irradiance.x < 1.0);
+
+        // 60° off the axis, outside of the cone
+        assert!(light
+            .sample(Pnt3::new((60.0f64).to_radians().tan(), 0.0, 0.0))
+            .is_none());
+        assert!(light.sample(Pnt3::new(0.0, 2.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn directional_light() {
+        let light = DirectionalLight::new(UnitVec3::new(0.0, -1.0, 0.0), Vec3::new(2.0, 2.0, 2.0));
+        let sample = light.sample(Pnt3::new(5.0, -3.0, 1.0)).unwrap();
+        assert_eq!(sample.wi, UnitVec3::new(0.0, 1.0, 0.0));
+        assert_eq!(sample.distance, f64::INFINITY);
+        assert_eq!(sample.irradiance, Vec3::new(2.0, 2.0, 2.0));
+    }
+}
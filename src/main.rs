@@ -1,18 +1,8 @@
-pub mod camera;
-pub mod color;
-pub mod image;
-pub mod interval;
-pub mod material;
-pub mod ray;
-pub mod scene;
-pub mod vec3;
-
 use anyhow::Result;
-use image::Image;
-use vec3::{Pnt3, UnitVec3, Vec3};
-
-#[macro_use]
-extern crate my_macro;
+use raytracer_rs::image::Image;
+use raytracer_rs::material::Material;
+use raytracer_rs::vec3::{Pnt3, UnitVec3, Vec3};
+use raytracer_rs::{camera, obj, scene};
 
 fn main() -> Result<()> {
     let mut cam = camera::Camera::look_at(
@@ -30,7 +20,7 @@ fn main() -> Result<()> {
     // cam.right.z = 1.0;
     // println!("{:?}", cam);
     let mut scene = scene::Scene::new();
-    scene.add_sphere(scene::sphere::Sphere::new(
+    scene.add(scene::sphere::Sphere::new(
         Pnt3 {
             x: 0.0,
             y: 0.0,
@@ -38,32 +28,48 @@ fn main() -> Result<()> {
         },
         66.6,
     ));
-    scene.add_cube(scene::cube::Cube::new(
-        Pnt3 {
-            x: -50.0,
-            y: 0.0,
-            z: 0.0,
-        },
-        Pnt3 {
-            x: 50.0,
-            y: 50.0,
-            z: 50.0,
-        },
-    ));
-    scene.add_plane(scene::plane::Plane::new(
-        Pnt3 {
-            x: 0.0,
-            y: -50.0,
-            z: 0.0,
-        },
-        UnitVec3 {
-            x: 0.0,
-            y: 1.0,
-            z: 0.0,
-        },
-    ));
+    scene.add(
+        scene::cube::Cube::new(
+            Pnt3 {
+                x: -50.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            Pnt3 {
+                x: 50.0,
+                y: 50.0,
+                z: 50.0,
+            },
+        )
+        .with_material(Material {
+            color: Vec3::new(255.0, 100.0, 100.0),
+            albedo: 0.1,
+            roughness: 0.8,
+            ..Default::default()
+        }),
+    );
+    scene.add(
+        scene::plane::Plane::new(
+            Pnt3 {
+                x: 0.0,
+                y: -50.0,
+                z: 0.0,
+            },
+            UnitVec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+        )
+        .with_material(Material {
+            color: Vec3::new(5.0, 150.0, 5.0),
+            albedo: 0.8,
+            roughness: 0.8,
+            ..Default::default()
+        }),
+    );
     // Show Coordinate system origin
-    scene.add_line(scene::line::Line::new(
+    scene.add(scene::line::Line::new(
         Pnt3 {
             x: 0.0,
             y: 0.0,
@@ -77,7 +83,7 @@ fn main() -> Result<()> {
         50.0,
         100.0,
     ));
-    scene.add_line(scene::line::Line::new(
+    scene.add(scene::line::Line::new(
         Pnt3 {
             x: 0.0,
             y: 0.0,
@@ -91,7 +97,7 @@ fn main() -> Result<()> {
         50.0,
         100.0,
     ));
-    scene.add_line(scene::line::Line::new(
+    scene.add(scene::line::Line::new(
         Pnt3 {
             x: 0.0,
             y: 0.0,
@@ -106,8 +112,15 @@ fn main() -> Result<()> {
         100.0,
     ));
 
+    // Add the meshes of an OBJ file given on the command line
+    if let Some(path) = std::env::args().nth(1) {
+        for mesh in obj::load(path)? {
+            scene.add(mesh);
+        }
+    }
+
     for i in 0..1 {
-        scene.add_sphere(scene::sphere::Sphere::new(
+        scene.add(scene::sphere::Sphere::new(
             Pnt3 {
                 x: 0.0,
                 y: 0.0,
@@ -117,8 +130,9 @@ fn main() -> Result<()> {
         ));
 
         cam.center.z += 10.0;
+        scene.build_bvh();
         let image = Image::gen_image(&cam, &scene, 1600, 900);
-        image.save_to_file(format!("/tmp/run_render.ppm").as_str())?;
+        image.save_to_file("/tmp/run_render.ppm")?;
     }
     Ok(())
 }
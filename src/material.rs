@@ -1,36 +1,404 @@
+use std::f64::consts::PI;
+
 use contracts::*;
 
-use crate::vec3::Vec3;
+use crate::ray::IntersectResult;
+use crate::sampling::{self, Frame};
+use crate::texture::TextureRef;
+use crate::vec3::{Pnt3, UnitVec3, Vec3};
+
+pub mod bump;
+pub mod conductor;
+pub mod dielectric;
+pub mod microfacet;
+pub mod principled;
+
+pub use conductor::ComplexIor;
+pub use principled::Principled;
+
+/// How a material scatters light.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum MaterialKind {
+    /// Lambertian reflection of the color.
+    #[default]
+    Diffuse,
+    /// Smooth glass or liquid, reflecting and refracting by the refractive index
+    /// and absorbing light inside by the absorption coefficient.
+    Dielectric,
+    /// Metal with the given complex refractive index, tinted by the color.
+    /// A mirror at roughness 0.0, glossy above.
+    Conductor(ComplexIor),
+    /// The Disney principled BSDF, with the color as base color.
+    Principled(Principled),
+}
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Material {
+    pub kind: MaterialKind,
     /// Color is the fraction of light reflected by a surface.
     /// 0.0 is black, 255.0 is white.
     pub color: Vec3,
     /// Albedo is the fraction of light reflected by a surface.
     /// 0.0 is black, 1.0 is white.
     pub albedo: f64,
-    /// Roughness is the fraction of light reflected in a random direction.
+    /// Roughness of glossy reflections, squared to the alpha of the GGX microfacet distribution.
     /// 0.0 is smooth, 1.0 is rough.
     pub roughness: f64,
     /// Refractive index of the material.
     /// 1.0 is air, 1.5 is glass, 2.42 is diamond.
     /// https://en.wikipedia.org/wiki/List_of_refractive_indices
     pub refractive_index: f64,
-    /// Absorption coefficient of the material.
-    /// 0.0 is transparent, 1.0 is opaque.
+    /// Absorption coefficient of the material, per unit of distance light travels inside it.
+    /// 0.0 is transparent, the higher the stronger the light is tinted by the color.
     /// https://en.wikipedia.org/wiki/Absorption_(electromagnetic_radiation)
     pub absorption_coefficient: f64,
+    /// Radiance emitted by the surface, on both of its sides.
+    /// Unlike `color` this is linear and unbounded, 1.0 matches a white background.
+    pub emission: Vec3,
+    /// Replaces `color` where set, with values from 0.0 to 255.0.
+    pub color_texture: Option<TextureRef>,
+    /// Replaces `roughness` with the first channel where set.
+    pub roughness_texture: Option<TextureRef>,
+    /// Replaces `emission` where set, with values from 0.0 to 255.0 scaled to 0.0 to 1.0
+    /// like colors, so image textures can be used as emitters.
+    pub emission_texture: Option<TextureRef>,
+    /// Tangent space normals replacing the surface normal where set, encoded like colors:
+    /// 0.0 to 255.0 per channel maps to -1.0 to 1.0 along the tangent, bitangent and normal.
+    /// Takes precedence over `bump_map`.
+    pub normal_map: Option<TextureRef>,
+    /// Height the surface is displaced by along its normal where set,
+    /// the first channel times `bump_scale`.
+    pub bump_map: Option<TextureRef>,
+    pub bump_scale: f64,
+}
+
+impl Material {
+    /// The default material, usable in constant contexts.
+    pub const DEFAULT: Material = Material {
+        kind: MaterialKind::Diffuse,
+        color: Vec3 {
+            x: 255.0,
+            y: 255.0,
+            z: 255.0,
+        },
+        albedo: 1.0,
+        roughness: 0.5,
+        refractive_index: 1.0,
+        absorption_coefficient: 0.0,
+        emission: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        color_texture: None,
+        roughness_texture: None,
+        emission_texture: None,
+        normal_map: None,
+        bump_map: None,
+        bump_scale: 1.0,
+    };
+
+    /// A clear dielectric, e.g. glass with a refractive index of 1.5.
+    #[requires(refractive_index > 0.0)]
+    pub fn dielectric(refractive_index: f64) -> Material {
+        Material {
+            kind: MaterialKind::Dielectric,
+            refractive_index,
+            ..Material::DEFAULT
+        }
+    }
+
+    /// A material authored with the parameters of the Disney principled BSDF.
+    pub fn principled(params: Principled) -> Material {
+        Material {
+            kind: MaterialKind::Principled(params),
+            ..Material::DEFAULT
+        }
+    }
+
+    /// A metal, e.g. [`ComplexIor::GOLD`], a perfect mirror at a roughness of 0.0.
+    #[requires((0.0..=1.0).contains(&roughness))]
+    pub fn conductor(ior: ComplexIor, roughness: f64) -> Material {
+        Material {
+            kind: MaterialKind::Conductor(ior),
+            roughness,
+            ..Material::DEFAULT
+        }
+    }
+}
+
+/// A direction sampled from the BSDF of a material.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BsdfSample {
+    /// Direction the light arrives from, pointing away from the surface.
+    pub wi: UnitVec3,
+    /// BSDF times cosine divided by the pdf, the factor the path throughput is scaled by.
+    pub weight: Vec3,
+    /// Probability density of having sampled `wi`, per solid angle.
+    /// For specular samples the probability of having picked this direction.
+    pub pdf: f64,
+    /// Whether `wi` is the only direction scattering towards `wo`, like for mirrors and glass.
+    pub specular: bool,
+}
+
+impl Material {
+    /// The material at a surface point, with its textured parameters looked up
+    /// and averaged over a region `footprint` wide in texture coordinates.
+    pub fn at(&self, uv: (f64, f64), p: Pnt3, footprint: f64) -> Material {
+        let mut material = self.clone();
+        if let Some(texture) = &self.color_texture {
+            material.color = texture.value(uv, p, footprint);
+        }
+        if let Some(texture) = &self.roughness_texture {
+            material.roughness = texture.value(uv, p, footprint).x.clamp(0.0, 1.0);
+        }
+        if let Some(texture) = &self.emission_texture {
+            material.emission = texture.value(uv, p, footprint) / 255.0;
+        }
+        material
+    }
+
+    /// The normal to shade `hit` at the point `p` with, perturbed by the normal or bump map.
+    /// Points to the same side as the geometric normal.
+    pub fn shading_normal(&self, hit: &IntersectResult, p: Pnt3) -> UnitVec3 {
+        if let Some(texture) = &self.normal_map {
+            bump::normal_map(texture, hit.normal, hit.dpdu, hit.dpdv, hit.uv, p)
+        } else if let Some(texture) = &self.bump_map {
+            bump::bump(
+                texture,
+                self.bump_scale,
+                hit.normal,
+                hit.dpdu,
+                hit.dpdv,
+                hit.uv,
+                p,
+            )
+        } else {
+            hit.normal
+        }
+    }
+
+    /// Fraction of light diffusely reflected per color channel, between 0.0 and 1.0.
+    pub fn reflectance(&self) -> Vec3 {
+        self.color * (self.albedo / 255.0)
+    }
+
+    /// Whether the surface emits light, making objects of this material area lights.
+    pub fn is_emissive(&self) -> bool {
+        self.emission != Vec3::null() || self.emission_texture.is_some()
+    }
+
+    /// Whether the material only scatters light in discrete directions,
+    /// so light sources can't be sampled for it.
+    pub fn is_specular(&self) -> bool {
+        match &self.kind {
+            MaterialKind::Diffuse => false,
+            MaterialKind::Dielectric => true,
+            MaterialKind::Conductor(_) => conductor::is_mirror(self),
+            MaterialKind::Principled(_) => false,
+        }
+    }
+
+    /// The BSDF times the cosine of `wi` to the normal,
+    /// the fraction of light arriving from `wi` that is scattered towards `wo`.
+    /// `normal` must be on the same side of the surface as `wo`.
+    /// Always black for specular materials.
+    #[requires(normal.dot(wo) >= 0.0)]
+    pub fn eval(&self, normal: UnitVec3, wo: UnitVec3, wi: UnitVec3) -> Vec3 {
+        match &self.kind {
+            MaterialKind::Diffuse => {
+                let cos_theta = normal.dot(wi);
+                if cos_theta <= 0.0 {
+                    return Vec3::null();
+                }
+                self.reflectance() * (cos_theta / PI)
+            }
+            MaterialKind::Dielectric => Vec3::null(),
+            MaterialKind::Conductor(ior) => conductor::eval(self, ior, normal, wo, wi),
+            MaterialKind::Principled(params) => principled::eval(self, params, normal, wo, wi),
+        }
+    }
+
+    /// Samples the direction light arrives from to be scattered towards `wo`.
+    /// `normal` must be on the same side of the surface as `wo`,
+    /// `front_face` tells whether that is the outside the surface normal pointed to.
+    /// Returns `None` if the path is absorbed.
+    #[requires(normal.dot(wo) >= 0.0)]
+    pub fn sample(
+        &self,
+        normal: UnitVec3,
+        wo: UnitVec3,
+        front_face: bool,
+        u: (f64, f64),
+    ) -> Option<BsdfSample> {
+        match &self.kind {
+            MaterialKind::Diffuse => self.sample_diffuse(normal, u),
+            MaterialKind::Dielectric => dielectric::sample(self, normal, wo, front_face, u),
+            MaterialKind::Conductor(ior) => conductor::sample(self, ior, normal, wo, u),
+            MaterialKind::Principled(params) => {
+                principled::sample(self, params, normal, wo, front_face, u)
+            }
+        }
+    }
+
+    /// Fraction of light per color channel left after travelling `distance` inside the material.
+    pub fn transmittance(&self, distance: f64) -> Vec3 {
+        match self.kind {
+            MaterialKind::Dielectric | MaterialKind::Principled(_) => {
+                dielectric::transmittance(self, distance)
+            }
+            MaterialKind::Diffuse | MaterialKind::Conductor(_) => Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    fn sample_diffuse(&self, normal: UnitVec3, u: (f64, f64)) -> Option<BsdfSample> {
+        let frame = Frame::from_normal(normal);
+        let local = sampling::cosine_hemisphere(u);
+        let pdf = sampling::cosine_hemisphere_pdf(local.z);
+        if pdf <= 0.0 {
+            return None;
+        }
+        // Lambertian BSDF `reflectance / PI` times `cos / pdf` cancels down to the reflectance
+        Some(BsdfSample {
+            wi: frame.to_world(local).normalize().ok()?,
+            weight: self.reflectance(),
+            pdf,
+            specular: false,
+        })
+    }
 }
 
 impl Default for Material {
     fn default() -> Self {
-        Material {
-            color: Vec3::new(255.0, 255.0, 255.0),
-            albedo: 1.0,
-            roughness: 0.5,
-            refractive_index: 1.0,
-            absorption_coefficient: 0.0,
+        Material::DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reflectance() {
+        let material = Material {
+            color: Vec3::new(255.0, 127.5, 0.0),
+            albedo: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(material.reflectance(), Vec3::new(0.5, 0.25, 0.0));
+    }
+
+    #[test]
+    fn diffuse_samples_stay_above_the_surface() {
+        let material = Material::default();
+        let normal = UnitVec3::new(0.0, 1.0, 1.0);
+        let wo = UnitVec3::new(0.0, 1.0, 0.0);
+        let mut rand = random::default(5);
+        for _ in 0..1000 {
+            let u = (
+                random::Source::read_f64(&mut rand),
+                random::Source::read_f64(&mut rand),
+            );
+            let sample = material.sample(normal, wo, true, u).unwrap();
+            assert!(sample.wi.dot(normal) >= 0.0);
+            assert_eq!(sample.weight, Vec3::new(1.0, 1.0, 1.0));
+            assert!((sample.pdf - sample.wi.dot(normal) / PI).abs() < 1e-9);
+            // The sampled weight is the evaluated BSDF divided by the pdf
+            let eval = material.eval(normal, wo, sample.wi);
+            assert!((eval / sample.pdf - sample.weight).len() < 1e-9);
         }
     }
+
+    #[test]
+    fn textured_parameters() {
+        use crate::texture::{Checkerboard, ConstantTexture};
+
+        let material = Material {
+            color_texture: Some(TextureRef::new(Checkerboard::new(
+                Vec3::new(255.0, 255.0, 255.0),
+                Vec3::null(),
+                2.0,
+            ))),
+            roughness_texture: Some(TextureRef::new(ConstantTexture::new(Vec3::new(
+                0.25, 0.0, 0.0,
+            )))),
+            ..Default::default()
+        };
+        let p = Pnt3::null();
+        assert_eq!(
+            material.at((0.1, 0.1), p, 0.0).color,
+            Vec3::new(255.0, 255.0, 255.0)
+        );
+        assert_eq!(material.at((0.6, 0.1), p, 0.0).color, Vec3::null());
+        assert_eq!(material.at((0.6, 0.1), p, 0.0).roughness, 0.25);
+        assert_eq!(material.at((0.6, 0.1), p, 0.0).emission, Vec3::null());
+        assert_eq!(
+            Material::default().at((0.6, 0.1), p, 0.0),
+            Material::default()
+        );
+    }
+
+    #[test]
+    fn image_textured_emission() {
+        use crate::color::Color;
+        use crate::texture::ImageTexture;
+
+        let material = Material {
+            emission_texture: Some(TextureRef::new(ImageTexture::from_colors(
+                2,
+                1,
+                &[Color::white(), Color::black()],
+            ))),
+            ..Default::default()
+        };
+        let p = Pnt3::null();
+        assert_eq!(
+            material.at((0.25, 0.5), p, 0.0).emission,
+            Vec3::new(1.0, 1.0, 1.0)
+        );
+        assert_eq!(material.at((0.75, 0.5), p, 0.0).emission, Vec3::null());
+    }
+
+    #[test]
+    fn shading_normals() {
+        use crate::texture::ConstantTexture;
+
+        let hit = IntersectResult {
+            t: 1.0,
+            normal: UnitVec3::new(0.0, 0.0, 1.0),
+            material: Default::default(),
+            uv: (0.5, 0.5),
+            dpdu: Vec3::new(1.0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, 1.0, 0.0),
+        };
+        let p = Pnt3::null();
+        assert_eq!(Material::default().shading_normal(&hit, p), hit.normal);
+
+        let tilted = TextureRef::new(ConstantTexture::new(Vec3::new(255.0, 127.5, 255.0)));
+        let flat = TextureRef::new(ConstantTexture::new(Vec3::new(1.0, 0.0, 0.0)));
+        let material = Material {
+            normal_map: Some(tilted),
+            bump_map: Some(flat),
+            ..Default::default()
+        };
+        // The normal map wins over the bump map
+        let normal = material.shading_normal(&hit, p);
+        assert!((normal.x - 0.5f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn diffuse_eval() {
+        let material = Material {
+            albedo: 0.5,
+            ..Default::default()
+        };
+        let normal = UnitVec3::new(0.0, 0.0, 1.0);
+        let eval = material.eval(normal, normal, UnitVec3::new(0.0, 0.0, 1.0));
+        assert!((eval - Vec3::new(0.5, 0.5, 0.5) / PI).len() < 1e-12);
+        assert_eq!(
+            material.eval(normal, normal, UnitVec3::new(0.0, 1.0, -1.0)),
+            Vec3::null()
+        );
+        assert!(!material.is_emissive());
+    }
 }
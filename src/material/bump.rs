@@ -0,0 +1,139 @@
+//! Normal and bump mapping, adding detail to a surface by perturbing its shading normal.
+//! https://pbr-book.org/3ed-2018/Materials/Bump_Mapping
+
+use crate::sampling::Frame;
+use crate::texture::TextureRef;
+use crate::vec3::{Pnt3, UnitVec3, Vec3};
+
+/// Step along the texture coordinates for the finite differences of bump maps.
+const DELTA: f64 = 1e-3;
+
+/// Frame with the tangent along `dpdu` and the bitangent on the side of `dpdv`.
+/// Surfaces without derivatives get an arbitrary frame around the normal.
+pub fn tangent_frame(normal: UnitVec3, dpdu: Vec3, dpdv: Vec3) -> Frame {
+    // Gram–Schmidt, the derivatives need not be perpendicular to the normal
+    let tangent = dpdu - Vec3::from(normal) * normal.dot(dpdu);
+    let Ok(tangent) = tangent.normalize() else {
+        return Frame::from_normal(normal);
+    };
+    let bitangent = normal.cross(tangent).normalize().unwrap();
+    let bitangent = if bitangent.dot(dpdv) < 0.0 {
+        -bitangent
+    } else {
+        bitangent
+    };
+    Frame {
+        tangent,
+        bitangent,
+        normal,
+    }
+}
+
+/// The normal read from a tangent space normal map, encoded like a color.
+pub fn normal_map(
+    texture: &TextureRef,
+    normal: UnitVec3,
+    dpdu: Vec3,
+    dpdv: Vec3,
+    uv: (f64, f64),
+    p: Pnt3,
+) -> UnitVec3 {
+    let local = texture.value(uv, p, 0.0) * (2.0 / 255.0) - Vec3::new(1.0, 1.0, 1.0);
+    tangent_frame(normal, dpdu, dpdv)
+        .to_world(local)
+        .normalize()
+        .unwrap_or(normal)
+}
+
+/// The normal of the surface displaced along it by the height in the first channel
+/// of `texture` times `scale`.
+pub fn bump(
+    texture: &TextureRef,
+    scale: f64,
+    normal: UnitVec3,
+    dpdu: Vec3,
+    dpdv: Vec3,
+    uv: (f64, f64),
+    p: Pnt3,
+) -> UnitVec3 {
+    let (dpdu, dpdv) = if dpdu.cross(dpdv) == Vec3::null() {
+        let frame = Frame::from_normal(normal);
+        (frame.tangent.into(), frame.bitangent.into())
+    } else {
+        (dpdu, dpdv)
+    };
+    let height = |uv: (f64, f64), p: Pnt3| texture.value(uv, p, 0.0).x * scale;
+    let center = height(uv, p);
+    let dhdu = (height((uv.0 + DELTA, uv.1), p + dpdu * DELTA) - center) / DELTA;
+    let dhdv = (height((uv.0, uv.1 + DELTA), p + dpdv * DELTA) - center) / DELTA;
+
+    let n = Vec3::from(normal);
+    let bumped = (dpdu + n * dhdu).cross(dpdv + n * dhdv);
+    // The cross product follows the orientation of the derivatives, not the normal
+    let bumped = if bumped.dot(n) < 0.0 { -bumped } else { bumped };
+    bumped.normalize().unwrap_or(normal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::{ConstantTexture, Texture};
+
+    /// A height of `slope * u`.
+    #[derive(Debug)]
+    struct Ramp {
+        slope: f64,
+    }
+
+    impl Texture for Ramp {
+        fn value(&self, uv: (f64, f64), _p: Pnt3, _footprint: f64) -> Vec3 {
+            Vec3::new(uv.0 * self.slope, 0.0, 0.0)
+        }
+    }
+
+    #[test]
+    fn flat_normal_maps_keep_the_normal() {
+        let texture = TextureRef::new(ConstantTexture::new(Vec3::new(127.5, 127.5, 255.0)));
+        let normal = UnitVec3::new(0.0, 1.0, 0.0);
+        let dpdu = Vec3::new(2.0, 0.5, 0.0);
+        let dpdv = Vec3::new(0.0, 0.0, -3.0);
+        let mapped = normal_map(&texture, normal, dpdu, dpdv, (0.0, 0.0), Pnt3::null());
+        assert!((Vec3::from(mapped) - Vec3::from(normal)).len() < 1e-12);
+    }
+
+    #[test]
+    fn normal_maps_tilt_towards_the_tangents() {
+        // Halfway between the normal and the tangent
+        let texture = TextureRef::new(ConstantTexture::new(Vec3::new(255.0, 127.5, 255.0)));
+        let normal = UnitVec3::new(0.0, 0.0, 1.0);
+        let dpdu = Vec3::new(0.0, 2.0, 0.0);
+        let dpdv = Vec3::new(-1.0, 0.0, 0.0);
+        let mapped = normal_map(&texture, normal, dpdu, dpdv, (0.0, 0.0), Pnt3::null());
+        let expected = Vec3::new(0.0, 1.0, 1.0) / 2f64.sqrt();
+        assert!((Vec3::from(mapped) - expected).len() < 1e-12);
+
+        let frame = tangent_frame(normal, dpdu, dpdv);
+        assert_eq!(frame.bitangent, UnitVec3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn bumps_tilt_against_the_slope() {
+        let normal = UnitVec3::new(0.0, 0.0, 1.0);
+        let dpdu = Vec3::new(1.0, 0.0, 0.0);
+        let dpdv = Vec3::new(0.0, 1.0, 0.0);
+        let p = Pnt3::null();
+
+        let flat = TextureRef::new(ConstantTexture::new(Vec3::new(3.0, 0.0, 0.0)));
+        assert_eq!(bump(&flat, 1.0, normal, dpdu, dpdv, (0.5, 0.5), p), normal);
+
+        // Rising by one along u tilts the normal by 45° towards -u
+        let ramp = TextureRef::new(Ramp { slope: 2.0 });
+        let bumped = bump(&ramp, 0.5, normal, dpdu, dpdv, (0.5, 0.5), p);
+        let expected = Vec3::new(-1.0, 0.0, 1.0) / 2f64.sqrt();
+        assert!((Vec3::from(bumped) - expected).len() < 1e-9);
+
+        // Independent of the orientation of the derivatives
+        let flipped = bump(&ramp, 0.5, normal, dpdu, -dpdv, (0.5, 0.5), p);
+        assert!((Vec3::from(flipped) - expected).len() < 1e-9);
+    }
+}
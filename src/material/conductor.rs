@@ -0,0 +1,216 @@
+//! Metals, reflecting light tinted by their complex refractive index.
+//! Mirrors at roughness 0, glossy GGX reflection above.
+//! https://pbr-book.org/3ed-2018/Reflection_Models/Microfacet_Models
+
+use contracts::*;
+
+use crate::material::microfacet::{self, MIN_ALPHA};
+use crate::material::{BsdfSample, Material};
+use crate::sampling::Frame;
+use crate::vec3::{UnitVec3, Vec3};
+
+/// Complex refractive index `eta + i k` per color channel.
+/// Presets are sampled at the red, green and blue wavelengths.
+/// https://refractiveindex.info
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ComplexIor {
+    pub eta: Vec3,
+    /// Extinction coefficient, how fast light is absorbed inside the metal.
+    pub k: Vec3,
+}
+
+impl ComplexIor {
+    pub const GOLD: ComplexIor =
+        ComplexIor::new_const((0.143, 0.375, 1.442), (3.983, 2.386, 1.603));
+    pub const SILVER: ComplexIor =
+        ComplexIor::new_const((0.155, 0.117, 0.138), (4.828, 3.122, 2.147));
+    pub const COPPER: ComplexIor =
+        ComplexIor::new_const((0.200, 0.924, 1.102), (3.913, 2.453, 2.142));
+    pub const ALUMINIUM: ComplexIor =
+        ComplexIor::new_const((1.657, 0.880, 0.521), (9.224, 6.270, 4.837));
+    pub const CHROMIUM: ComplexIor =
+        ComplexIor::new_const((4.370, 2.917, 1.655), (5.206, 4.231, 3.755));
+    pub const IRON: ComplexIor =
+        ComplexIor::new_const((2.911, 2.950, 2.585), (3.089, 2.932, 2.767));
+
+    pub fn new(eta: Vec3, k: Vec3) -> ComplexIor {
+        ComplexIor { eta, k }
+    }
+
+    const fn new_const(eta: (f64, f64, f64), k: (f64, f64, f64)) -> ComplexIor {
+        ComplexIor {
+            eta: Vec3 {
+                x: eta.0,
+                y: eta.1,
+                z: eta.2,
+            },
+            k: Vec3 {
+                x: k.0,
+                y: k.1,
+                z: k.2,
+            },
+        }
+    }
+
+    /// Fraction of light reflected per color channel at an angle with cosine `cos_i` to the normal.
+    pub fn fresnel(&self, cos_i: f64) -> Vec3 {
+        Vec3::new(
+            fresnel_conductor(cos_i, self.eta.x, self.k.x),
+            fresnel_conductor(cos_i, self.eta.y, self.k.y),
+            fresnel_conductor(cos_i, self.eta.z, self.k.z),
+        )
+    }
+}
+
+/// Fresnel reflectance of unpolarized light on a conductor, from air.
+/// https://pbr-book.org/3ed-2018/Reflection_Models/Specular_Reflection_and_Transmission#FresnelReflectance
+pub fn fresnel_conductor(cos_i: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let t0 = eta * eta - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cos_i.clamp(0.0, 1.0) * a;
+    let r_s = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let r_p = r_s * (t3 - t4) / (t3 + t4);
+    (r_p + r_s) / 2.0
+}
+
+/// Whether the conductor is smooth enough to be a perfect mirror.
+pub fn is_mirror(material: &Material) -> bool {
+    microfacet::alpha(material.roughness) < MIN_ALPHA
+}
+
+/// The BSDF times the cosine of `wi`, the color tints the reflection.
+#[requires(normal.dot(wo) >= 0.0)]
+pub fn eval(
+    material: &Material,
+    ior: &ComplexIor,
+    normal: UnitVec3,
+    wo: UnitVec3,
+    wi: UnitVec3,
+) -> Vec3 {
+    if is_mirror(material) {
+        return Vec3::null();
+    }
+    let alpha = microfacet::alpha(material.roughness);
+    let frame = Frame::from_normal(normal);
+    let wo = frame.to_local(wo.into());
+    let wi = frame.to_local(wi.into());
+    if wo.z <= 0.0 || wi.z <= 0.0 {
+        return Vec3::null();
+    }
+    let h = wo + wi;
+    let h = h / h.len();
+    // D * G * F / (4 cos_o cos_i), times cos_i
+    let scale = microfacet::distribution(h, alpha) * microfacet::masking_shadowing(wo, wi, alpha)
+        / (4.0 * wo.z);
+    ior.fresnel(wi.dot(h)) * material.reflectance() * scale
+}
+
+/// Samples the mirror direction, or a direction reflected off a visible GGX microfacet.
+#[requires(normal.dot(wo) >= 0.0)]
+pub fn sample(
+    material: &Material,
+    ior: &ComplexIor,
+    normal: UnitVec3,
+    wo: UnitVec3,
+    u: (f64, f64),
+) -> Option<BsdfSample> {
+    let frame = Frame::from_normal(normal);
+    let wo_local = frame.to_local(wo.into());
+    if is_mirror(material) {
+        let wi = microfacet::reflect(wo_local, Vec3::new(0.0, 0.0, 1.0));
+        return Some(BsdfSample {
+            wi: frame.to_world(wi).normalize().ok()?,
+            weight: ior.fresnel(wo_local.z) * material.reflectance(),
+            pdf: 1.0,
+            specular: true,
+        });
+    }
+
+    let alpha = microfacet::alpha(material.roughness);
+    let h = microfacet::sample_visible_normal(wo_local, alpha, u);
+    let wi = microfacet::reflect(wo_local, h);
+    if wi.z <= 0.0 {
+        return None;
+    }
+    // D * G * F / (4 cos_o cos_i) * cos_i over the visible normal pdf leaves F * G / G1
+    let weight = ior.fresnel(wi.dot(h))
+        * material.reflectance()
+        * (microfacet::masking_shadowing(wo_local, wi, alpha)
+            / microfacet::masking(wo_local, alpha));
+    Some(BsdfSample {
+        wi: frame.to_world(wi).normalize().ok()?,
+        weight,
+        pdf: microfacet::reflection_pdf(wo_local, wi, alpha),
+        specular: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reflectance_at_normal_incidence() {
+        // ((n - 1)² + k²) / ((n + 1)² + k²)
+        let expected = (0.5f64.powi(2) + 4.0) / (2.5f64.powi(2) + 4.0);
+        assert!((fresnel_conductor(1.0, 1.5, 2.0) - expected).abs() < 1e-12);
+        // Without extinction a conductor is a dielectric
+        assert!(
+            (fresnel_conductor(0.6, 1.5, 0.0) - super::super::dielectric::fresnel(0.6, 1.5)).abs()
+                < 1e-12
+        );
+        assert!((fresnel_conductor(0.0, 1.5, 2.0) - 1.0).abs() < 1e-12);
+
+        // Gold reflects more red than blue
+        let gold = ComplexIor::GOLD.fresnel(1.0);
+        assert!(gold.x > 0.9 && gold.z < 0.5);
+        let silver = ComplexIor::SILVER.fresnel(1.0);
+        assert!(silver.x > 0.9 && silver.z > 0.9);
+    }
+
+    #[test]
+    fn mirror() {
+        let mirror = Material::conductor(ComplexIor::SILVER, 0.0);
+        let normal = UnitVec3::new(0.0, 0.0, 1.0);
+        let wo = UnitVec3::new(1.0, 0.0, 1.0);
+        let sample = mirror.sample(normal, wo, true, (0.3, 0.7)).unwrap();
+        assert!(sample.specular);
+        assert!((Vec3::from(sample.wi) - Vec3::from(UnitVec3::new(-1.0, 0.0, 1.0))).len() < 1e-12);
+        assert!(mirror.is_specular());
+        assert_eq!(mirror.eval(normal, wo, sample.wi), Vec3::null());
+    }
+
+    #[test]
+    fn glossy_samples_match_eval() {
+        let metal = Material::conductor(ComplexIor::GOLD, 0.5);
+        assert!(!metal.is_specular());
+        let normal = UnitVec3::new(0.0, 1.0, 0.0);
+        let wo = UnitVec3::new(0.3, 1.0, 0.2);
+        let mut rand = random::default(42);
+        let mut total = Vec3::null();
+        let n = 10000;
+        for _ in 0..n {
+            let u = (
+                random::Source::read_f64(&mut rand),
+                random::Source::read_f64(&mut rand),
+            );
+            let Some(sample) = metal.sample(normal, wo, true, u) else {
+                continue;
+            };
+            assert!(!sample.specular);
+            assert!(sample.wi.dot(normal) > 0.0);
+            let eval = metal.eval(normal, wo, sample.wi);
+            assert!((eval / sample.pdf - sample.weight).len() < 1e-6);
+            total = total + sample.weight;
+        }
+        // Some energy is lost to shadowing, but never gained
+        let albedo = total / n as f64;
+        assert!(albedo.x < 1.0 && albedo.x > 0.8);
+    }
+}
@@ -0,0 +1,178 @@
+//! Smooth dielectric interfaces like glass and water, which reflect and refract light.
+//! https://pbr-book.org/3ed-2018/Reflection_Models/Specular_Reflection_and_Transmission
+
+use contracts::*;
+
+use crate::material::{BsdfSample, Material};
+use crate::vec3::{UnitVec3, Vec3};
+
+/// Mirrors `wo` about the normal.
+pub fn reflect(wo: UnitVec3, normal: UnitVec3) -> Vec3 {
+    normal * (2.0 * wo.dot(normal)) - Vec3::from(wo)
+}
+
+/// Bends `wo` through the surface by Snell's law.
+/// `eta` is the refractive index on the far side divided by the one on the side of `wo`.
+/// Returns `None` on total internal reflection.
+/// https://en.wikipedia.org/wiki/Snell%27s_law#Vector_form
+#[requires(normal.dot(wo) >= 0.0)]
+pub fn refract(wo: UnitVec3, normal: UnitVec3, eta: f64) -> Option<Vec3> {
+    let cos_i = normal.dot(wo);
+    let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some(wo * (-1.0 / eta) + normal * (cos_i / eta - cos_t))
+}
+
+/// Fraction of unpolarized light reflected by the interface, the rest is refracted.
+/// `cos_i` is the cosine of the incident direction to the normal,
+/// `eta` the ratio of refractive indices as for [`refract`].
+/// https://en.wikipedia.org/wiki/Fresnel_equations
+pub fn fresnel(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+/// Schlick's cheaper approximation of [`fresnel`].
+/// https://en.wikipedia.org/wiki/Schlick%27s_approximation
+pub fn schlick(cos_i: f64, eta: f64) -> f64 {
+    let r0 = ((1.0 - eta) / (1.0 + eta)).powi(2);
+    // Leaving the denser medium, the angle on the outside determines the reflectance
+    let cos = if eta < 1.0 {
+        let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+        if sin2_t >= 1.0 {
+            return 1.0;
+        }
+        (1.0 - sin2_t).sqrt()
+    } else {
+        cos_i
+    };
+    r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+}
+
+/// Picks reflection or refraction with the Fresnel reflectance as probability.
+/// `front_face` tells whether `wo` is outside of the material.
+#[requires(normal.dot(wo) >= 0.0)]
+pub fn sample(
+    material: &Material,
+    normal: UnitVec3,
+    wo: UnitVec3,
+    front_face: bool,
+    u: (f64, f64),
+) -> Option<BsdfSample> {
+    let eta = if front_face {
+        material.refractive_index
+    } else {
+        1.0 / material.refractive_index
+    };
+    let reflectance = fresnel(normal.dot(wo), eta);
+    // The probability of each choice cancels with its share of the light
+    let (wi, pdf) = if u.0 < reflectance {
+        (reflect(wo, normal), reflectance)
+    } else {
+        (refract(wo, normal, eta)?, 1.0 - reflectance)
+    };
+    Some(BsdfSample {
+        wi: wi.normalize().ok()?,
+        weight: Vec3::new(1.0, 1.0, 1.0),
+        pdf,
+        specular: true,
+    })
+}
+
+/// Fraction of light per color channel left after travelling `distance` through the material.
+/// Beer–Lambert law, where the color is what remains after a distance of
+/// `1 / absorption_coefficient`.
+/// https://en.wikipedia.org/wiki/Beer%E2%80%93Lambert_law
+#[requires(distance >= 0.0)]
+pub fn transmittance(material: &Material, distance: f64) -> Vec3 {
+    let depth = material.absorption_coefficient * distance;
+    let channel = |c: f64| (c / 255.0).clamp(0.0, 1.0).powf(depth);
+    Vec3::new(
+        channel(material.color.x),
+        channel(material.color.y),
+        channel(material.color.z),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresnel_reflectance() {
+        // 4% at normal incidence on glass, from either side
+        assert!((fresnel(1.0, 1.5) - 0.04).abs() < 1e-12);
+        assert!((fresnel(1.0, 1.0 / 1.5) - 0.04).abs() < 1e-12);
+        assert!((schlick(1.0, 1.5) - 0.04).abs() < 1e-12);
+        // Everything at grazing angles
+        assert!((fresnel(0.0, 1.5) - 1.0).abs() < 1e-12);
+        assert!((schlick(0.0, 1.5) - 1.0).abs() < 1e-12);
+        // Total internal reflection beyond the critical angle of ~41.8°
+        let cos_45 = std::f64::consts::FRAC_1_SQRT_2;
+        assert_eq!(fresnel(cos_45, 1.0 / 1.5), 1.0);
+        assert_eq!(schlick(cos_45, 1.0 / 1.5), 1.0);
+        // No interface, no reflection
+        assert!(fresnel(0.5, 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn snells_law() {
+        let normal = UnitVec3::new(0.0, 0.0, 1.0);
+        let wo = UnitVec3::new(1.0, 0.0, 1.0);
+        let wi = refract(wo, normal, 1.5).unwrap();
+        assert!((wi.len() - 1.0).abs() < 1e-12);
+        // sin(theta_t) * 1.5 == sin(theta_i)
+        let sin_t = (wi.x * wi.x + wi.y * wi.y).sqrt();
+        assert!((sin_t * 1.5 - std::f64::consts::FRAC_1_SQRT_2).abs() < 1e-12);
+        assert!(wi.x < 0.0 && wi.z < 0.0);
+
+        assert!(refract(wo, normal, 1.0 / 1.5).is_none());
+        assert_eq!(
+            reflect(wo, normal),
+            Vec3::from(UnitVec3::new(-1.0, 0.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn samples_reflect_or_refract() {
+        let glass = Material::dielectric(1.5);
+        let normal = UnitVec3::new(0.0, 0.0, 1.0);
+        let wo = UnitVec3::new(0.0, 0.0, 1.0);
+
+        let reflected = sample(&glass, normal, wo, true, (0.01, 0.5)).unwrap();
+        assert_eq!(reflected.wi, wo);
+        assert!((reflected.pdf - 0.04).abs() < 1e-12);
+        assert!(reflected.specular);
+
+        let refracted = sample(&glass, normal, wo, true, (0.5, 0.5)).unwrap();
+        assert_eq!(refracted.wi, -wo);
+        assert!((refracted.pdf - 0.96).abs() < 1e-12);
+        assert_eq!(refracted.weight, Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn beer_lambert() {
+        let tinted = Material {
+            color: Vec3::new(255.0, 127.5, 0.0),
+            absorption_coefficient: 0.5,
+            ..Material::dielectric(1.5)
+        };
+        assert_eq!(transmittance(&tinted, 0.0), Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(transmittance(&tinted, 2.0), Vec3::new(1.0, 0.5, 0.0));
+        assert_eq!(transmittance(&tinted, 4.0), Vec3::new(1.0, 0.25, 0.0));
+        assert_eq!(
+            transmittance(&Material::dielectric(1.5), 100.0),
+            Vec3::new(1.0, 1.0, 1.0)
+        );
+    }
+}
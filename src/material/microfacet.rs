@@ -0,0 +1,162 @@
+//! The GGX (Trowbridge–Reitz) microfacet distribution for glossy reflection off rough surfaces.
+//! Directions are local to the surface, with the normal as z axis.
+//! https://pbr-book.org/3ed-2018/Reflection_Models/Microfacet_Models
+
+use std::f64::consts::PI;
+
+use contracts::*;
+
+use crate::vec3::Vec3;
+
+/// Below this alpha surfaces are treated as perfectly smooth,
+/// the distribution degenerates numerically.
+pub const MIN_ALPHA: f64 = 1e-3;
+
+/// Maps the perceptual roughness of a material to the GGX alpha.
+#[requires((0.0..=1.0).contains(&roughness))]
+pub fn alpha(roughness: f64) -> f64 {
+    roughness * roughness
+}
+
+/// Density of microfacets with the normal `h`.
+pub fn distribution(h: Vec3, alpha: f64) -> f64 {
+    if h.z <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    let denom = h.z * h.z * (a2 - 1.0) + 1.0;
+    a2 / (PI * denom * denom)
+}
+
+/// Smith's auxiliary function, the ratio of hidden to visible microfacet area seen from `w`.
+fn lambda(w: Vec3, alpha: f64) -> f64 {
+    let cos2 = w.z * w.z;
+    if cos2 <= 0.0 {
+        return f64::INFINITY;
+    }
+    let tan2 = (1.0 - cos2).max(0.0) / cos2;
+    ((1.0 + alpha * alpha * tan2).sqrt() - 1.0) / 2.0
+}
+
+/// Fraction of microfacets visible from `w`.
+pub fn masking(w: Vec3, alpha: f64) -> f64 {
+    1.0 / (1.0 + lambda(w, alpha))
+}
+
+/// Fraction of microfacets visible from both `wo` and `wi`, height correlated.
+pub fn masking_shadowing(wo: Vec3, wi: Vec3, alpha: f64) -> f64 {
+    1.0 / (1.0 + lambda(wo, alpha) + lambda(wi, alpha))
+}
+
+/// Samples a microfacet normal visible from `wo`.
+/// https://jcgt.org/published/0007/04/01/
+pub fn sample_visible_normal(wo: Vec3, alpha: f64, u: (f64, f64)) -> Vec3 {
+    // Stretch the view direction to the hemisphere configuration
+    let vh = Vec3::new(alpha * wo.x, alpha * wo.y, wo.z);
+    let vh = vh / vh.len();
+    let len2 = vh.x * vh.x + vh.y * vh.y;
+    let t1 = if len2 > 0.0 {
+        Vec3::new(-vh.y, vh.x, 0.0) / len2.sqrt()
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let t2 = vh.cross(t1);
+
+    // Sample the projected area of the visible hemisphere
+    let r = u.0.sqrt();
+    let phi = 2.0 * PI * u.1;
+    let p1 = r * phi.cos();
+    let s = 0.5 * (1.0 + vh.z);
+    let p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * r * phi.sin();
+    let nh = t1 * p1 + t2 * p2 + vh * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+
+    // Unstretch back to the ellipsoid configuration
+    let h = Vec3::new(alpha * nh.x, alpha * nh.y, nh.z.max(1e-9));
+    h / h.len()
+}
+
+/// Density of [`sample_visible_normal`] followed by reflecting `wo` about the normal,
+/// per solid angle of `wi`.
+pub fn reflection_pdf(wo: Vec3, wi: Vec3, alpha: f64) -> f64 {
+    let h = wo + wi;
+    if wo.z <= 0.0 || wi.z <= 0.0 || h == Vec3::null() {
+        return 0.0;
+    }
+    let h = h / h.len();
+    let wo_dot_h = wo.dot(h);
+    if wo_dot_h <= 0.0 {
+        return 0.0;
+    }
+    let visible = masking(wo, alpha) * wo_dot_h * distribution(h, alpha) / wo.z;
+    visible / (4.0 * wo_dot_h)
+}
+
+/// Reflects `w` about the microfacet normal `h`.
+pub fn reflect(w: Vec3, h: Vec3) -> Vec3 {
+    h * (2.0 * w.dot(h)) - w
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform(rand: &mut impl random::Source) -> (f64, f64) {
+        (rand.read_f64(), rand.read_f64())
+    }
+
+    #[test]
+    fn distribution_is_normalized() {
+        // The projected microfacet area covers the macro surface exactly once
+        let alpha = 0.3;
+        let mut rand = random::default(42);
+        let n = 200000;
+        let integral = (0..n)
+            .map(|_| {
+                let h = crate::sampling::uniform_sphere(uniform(&mut rand));
+                distribution(h, alpha) * h.z.max(0.0) * 4.0 * PI
+            })
+            .sum::<f64>()
+            / n as f64;
+        assert!((integral - 1.0).abs() < 0.03);
+    }
+
+    #[test]
+    fn visible_normals_face_the_viewer() {
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        let mut rand = random::default(42);
+        for _ in 0..1000 {
+            let h = sample_visible_normal(wo, 0.5, uniform(&mut rand));
+            assert!((h.len() - 1.0).abs() < 1e-9);
+            assert!(h.z > 0.0);
+            assert!(wo.dot(h) >= 0.0);
+        }
+    }
+
+    #[test]
+    fn reflection_pdf_matches_sampling() {
+        // Integrates to the fraction of sampled reflections that stay above the surface
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        let alpha = 0.5;
+        let mut rand = random::default(42);
+        let n = 200000;
+        let integral = (0..n)
+            .map(|_| {
+                let wi = crate::sampling::uniform_sphere(uniform(&mut rand));
+                reflection_pdf(wo, wi, alpha) * 4.0 * PI
+            })
+            .sum::<f64>()
+            / n as f64;
+        let above = (0..n)
+            .filter(|_| reflect(wo, sample_visible_normal(wo, alpha, uniform(&mut rand))).z > 0.0)
+            .count();
+        assert!((integral - above as f64 / n as f64).abs() < 0.01);
+    }
+
+    #[test]
+    fn smooth_surfaces_hide_nothing() {
+        let wo = Vec3::new(0.6, 0.0, 0.8);
+        assert!((masking(wo, MIN_ALPHA) - 1.0).abs() < 1e-6);
+        assert!(masking(wo, 1.0) < 1.0);
+        assert_eq!(alpha(0.5), 0.25);
+    }
+}
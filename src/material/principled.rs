@@ -0,0 +1,413 @@
+//! The Disney principled BSDF, combining diffuse, sheen, specular, clearcoat and transmission
+//! lobes controlled by artist friendly parameters.
+//! https://media.disneyanimation.com/uploads/production/publication_asset/48/asset/s2012_pbs_disney_brdf_notes_v3.pdf
+//! https://blog.selfshadow.com/publications/s2015-shading-course/burley/s2015_pbs_disney_bsdf_notes.pdf
+
+use std::f64::consts::PI;
+
+use contracts::*;
+
+use crate::material::microfacet::{self, MIN_ALPHA};
+use crate::material::{dielectric, BsdfSample, Material};
+use crate::sampling::{self, Frame};
+use crate::vec3::{UnitVec3, Vec3};
+
+/// Parameters of the principled BSDF, all between 0.0 and 1.0.
+/// Base color and roughness are the `color`, `albedo` and `roughness` of the [`Material`],
+/// transmitted light refracts by its `refractive_index`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Principled {
+    /// Blends from a dielectric to a metal, which reflects tinted by the base color.
+    pub metallic: f64,
+    /// Strength of the specular reflection of dielectrics, 0.5 is 4%.
+    pub specular: f64,
+    /// Tints the dielectric specular reflection towards the base color.
+    pub specular_tint: f64,
+    /// Strength of the retro-reflective sheen at grazing angles, for cloth.
+    pub sheen: f64,
+    /// Tints the sheen towards the base color.
+    pub sheen_tint: f64,
+    /// Strength of a second, white, specular layer like varnish.
+    pub clearcoat: f64,
+    /// Glossiness of the clearcoat, from satin at 0.0 to gloss at 1.0.
+    pub clearcoat_gloss: f64,
+    /// Blends from an opaque to a fully transmissive dielectric, like glass.
+    pub transmission: f64,
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Principled {
+            metallic: 0.0,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+        }
+    }
+}
+
+/// Roughness of the clearcoat layer's masking, fixed by the Disney model.
+const CLEARCOAT_ROUGHNESS: f64 = 0.25;
+
+/// The lobes of a principled material with their sampling probabilities.
+struct Lobes {
+    base_color: Vec3,
+    alpha: f64,
+    params: Principled,
+    diffuse: f64,
+    specular: f64,
+    clearcoat: f64,
+    transmission: f64,
+}
+
+impl Lobes {
+    fn new(material: &Material, params: &Principled) -> Lobes {
+        let diffuse = (1.0 - params.metallic) * (1.0 - params.transmission);
+        let specular = 1.0;
+        let clearcoat = 0.25 * params.clearcoat;
+        let transmission = (1.0 - params.metallic) * params.transmission;
+        let total = diffuse + specular + clearcoat + transmission;
+        Lobes {
+            base_color: material.reflectance(),
+            alpha: microfacet::alpha(material.roughness).max(MIN_ALPHA),
+            params: *params,
+            diffuse: diffuse / total,
+            specular: specular / total,
+            clearcoat: clearcoat / total,
+            transmission: transmission / total,
+        }
+    }
+
+    /// The base color normalized to a luminance of 1.0, to tint without darkening.
+    fn tint(&self) -> Vec3 {
+        let luminance = luminance(self.base_color);
+        if luminance > 0.0 {
+            self.base_color / luminance
+        } else {
+            Vec3::new(1.0, 1.0, 1.0)
+        }
+    }
+
+    /// Reflectance of the specular lobe at normal incidence.
+    fn specular_color(&self) -> Vec3 {
+        let dielectric = lerp(
+            Vec3::new(1.0, 1.0, 1.0),
+            self.tint(),
+            self.params.specular_tint,
+        ) * (0.08 * self.params.specular);
+        lerp(dielectric, self.base_color, self.params.metallic)
+    }
+
+    fn clearcoat_alpha(&self) -> f64 {
+        0.1 + (0.001 - 0.1) * self.params.clearcoat_gloss
+    }
+
+    /// The BSDF of all but the transmission lobe, times the cosine of `wi`.
+    fn eval(&self, wo: Vec3, wi: Vec3) -> Vec3 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Vec3::null();
+        }
+        let h = (wo + wi) / (wo + wi).len();
+        let cos_d = wi.dot(h);
+        let params = &self.params;
+
+        // Burley diffuse with retro-reflection at grazing angles, plus sheen
+        let fd90 = 0.5 + 2.0 * self.alpha.sqrt() * cos_d * cos_d;
+        let fd = (1.0 + (fd90 - 1.0) * schlick_weight(wi.z))
+            * (1.0 + (fd90 - 1.0) * schlick_weight(wo.z));
+        let sheen_color = lerp(Vec3::new(1.0, 1.0, 1.0), self.tint(), params.sheen_tint);
+        let diffuse = self.base_color * (fd / PI * (1.0 - params.transmission))
+            + sheen_color * (params.sheen * schlick_weight(cos_d));
+        let diffuse = diffuse * (1.0 - params.metallic);
+
+        // GGX specular reflection with Schlick's Fresnel
+        let specular_color = self.specular_color();
+        let fresnel =
+            specular_color + (Vec3::new(1.0, 1.0, 1.0) - specular_color) * schlick_weight(cos_d);
+        let specular = fresnel
+            * (microfacet::distribution(h, self.alpha)
+                * microfacet::masking_shadowing(wo, wi, self.alpha)
+                / (4.0 * wo.z * wi.z));
+
+        // Clearcoat with the GTR1 distribution and a fixed index of refraction of 1.5
+        let clearcoat = if params.clearcoat > 0.0 {
+            0.25 * params.clearcoat
+                * gtr1(h.z, self.clearcoat_alpha())
+                * (0.04 + 0.96 * schlick_weight(cos_d))
+                * microfacet::masking(wo, CLEARCOAT_ROUGHNESS)
+                * microfacet::masking(wi, CLEARCOAT_ROUGHNESS)
+                / (4.0 * wo.z * wi.z)
+        } else {
+            0.0
+        };
+
+        (diffuse + specular + Vec3::new(clearcoat, clearcoat, clearcoat)) * wi.z
+    }
+
+    /// Density of sampling `wi` with all but the transmission lobe, per solid angle.
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi) / (wo + wi).len();
+        let clearcoat = if self.clearcoat > 0.0 {
+            gtr1(h.z, self.clearcoat_alpha()) * h.z / (4.0 * wo.dot(h))
+        } else {
+            0.0
+        };
+        self.diffuse * sampling::cosine_hemisphere_pdf(wi.z)
+            + self.specular * microfacet::reflection_pdf(wo, wi, self.alpha)
+            + self.clearcoat * clearcoat
+    }
+}
+
+/// The BSDF times the cosine of `wi`, without the specular transmission.
+#[requires(normal.dot(wo) >= 0.0)]
+pub fn eval(
+    material: &Material,
+    params: &Principled,
+    normal: UnitVec3,
+    wo: UnitVec3,
+    wi: UnitVec3,
+) -> Vec3 {
+    let frame = Frame::from_normal(normal);
+    Lobes::new(material, params).eval(frame.to_local(wo.into()), frame.to_local(wi.into()))
+}
+
+/// Picks one of the lobes, `u.0` chooses the lobe and is reused to sample it.
+#[requires(normal.dot(wo) >= 0.0)]
+pub fn sample(
+    material: &Material,
+    params: &Principled,
+    normal: UnitVec3,
+    wo: UnitVec3,
+    front_face: bool,
+    u: (f64, f64),
+) -> Option<BsdfSample> {
+    let lobes = Lobes::new(material, params);
+    let frame = Frame::from_normal(normal);
+    let wo_local = frame.to_local(wo.into());
+
+    if u.0 < lobes.transmission {
+        return sample_transmission(material, &lobes, normal, wo, front_face);
+    }
+    let non_delta = 1.0 - lobes.transmission;
+    let u0 = ((u.0 - lobes.transmission) / non_delta).clamp(0.0, 1.0);
+
+    let wi = if u0 < lobes.diffuse / non_delta {
+        let u0 = u0 / (lobes.diffuse / non_delta);
+        sampling::cosine_hemisphere((u0, u.1))
+    } else if u0 < (lobes.diffuse + lobes.specular) / non_delta {
+        let u0 = (u0 - lobes.diffuse / non_delta) / (lobes.specular / non_delta);
+        let h = microfacet::sample_visible_normal(wo_local, lobes.alpha, (u0.min(1.0), u.1));
+        microfacet::reflect(wo_local, h)
+    } else {
+        // Without clearcoat, rounding can still land u0 == 1.0 here
+        let start = (lobes.diffuse + lobes.specular) / non_delta;
+        let u0 = if 1.0 - start > 0.0 {
+            ((u0 - start) / (1.0 - start)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let h = sample_gtr1(lobes.clearcoat_alpha(), (u0, u.1));
+        microfacet::reflect(wo_local, h)
+    };
+    if wi.z <= 0.0 {
+        return None;
+    }
+    let pdf = lobes.pdf(wo_local, wi);
+    if pdf <= 0.0 {
+        return None;
+    }
+    Some(BsdfSample {
+        wi: frame.to_world(wi).normalize().ok()?,
+        weight: lobes.eval(wo_local, wi) / pdf,
+        pdf,
+        specular: false,
+    })
+}
+
+/// Refracts through the smooth dielectric interface, tinted by the base color.
+fn sample_transmission(
+    material: &Material,
+    lobes: &Lobes,
+    normal: UnitVec3,
+    wo: UnitVec3,
+    front_face: bool,
+) -> Option<BsdfSample> {
+    let eta = if front_face {
+        material.refractive_index
+    } else {
+        1.0 / material.refractive_index
+    };
+    // Reflections off the interface are covered by the specular lobe, except total internal ones
+    let (wi, fraction) = match dielectric::refract(wo, normal, eta) {
+        Some(wi) => (wi, 1.0 - dielectric::fresnel(normal.dot(wo), eta)),
+        None => (dielectric::reflect(wo, normal), 1.0),
+    };
+    // Light entering and leaving an object passes the surface twice, tinting it once in total
+    let base = lobes.base_color;
+    let tint = Vec3::new(base.x.sqrt(), base.y.sqrt(), base.z.sqrt());
+    let strength = (1.0 - lobes.params.metallic) * lobes.params.transmission;
+    Some(BsdfSample {
+        wi: wi.normalize().ok()?,
+        weight: tint * (strength * fraction / lobes.transmission),
+        pdf: lobes.transmission,
+        specular: true,
+    })
+}
+
+/// Weight of Schlick's Fresnel approximation, `(1 - cos)^5`.
+fn schlick_weight(cos: f64) -> f64 {
+    (1.0 - cos).clamp(0.0, 1.0).powi(5)
+}
+
+/// The "generalized Trowbridge–Reitz" distribution with an exponent of 1, for the clearcoat.
+fn gtr1(cos_h: f64, alpha: f64) -> f64 {
+    if cos_h <= 0.0 {
+        return 0.0;
+    }
+    let a2 = alpha * alpha;
+    (a2 - 1.0) / (PI * a2.ln() * (1.0 + (a2 - 1.0) * cos_h * cos_h))
+}
+
+/// Samples a microfacet normal with a density of `gtr1(h.z) * h.z`.
+fn sample_gtr1(alpha: f64, u: (f64, f64)) -> Vec3 {
+    let a2 = alpha * alpha;
+    let cos2 = ((1.0 - a2.powf(1.0 - u.0)) / (1.0 - a2)).clamp(0.0, 1.0);
+    let sin = (1.0 - cos2).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(sin * phi.cos(), sin * phi.sin(), cos2.sqrt())
+}
+
+fn luminance(color: Vec3) -> f64 {
+    0.3 * color.x + 0.6 * color.y + 0.1 * color.z
+}
+
+fn lerp(a: Vec3, b: Vec3, t: f64) -> Vec3 {
+    a * (1.0 - t) + b * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform(rand: &mut impl random::Source) -> (f64, f64) {
+        (rand.read_f64(), rand.read_f64())
+    }
+
+    /// Checks sampled weights against the evaluated BSDF and returns the mean weight.
+    fn check_samples(material: &Material) -> Vec3 {
+        let normal = UnitVec3::new(0.0, 0.0, 1.0);
+        let wo = UnitVec3::new(0.4, -0.2, 1.0);
+        let mut rand = random::default(42);
+        let n = 20000;
+        let mut total = Vec3::null();
+        for _ in 0..n {
+            let Some(sample) = material.sample(normal, wo, true, uniform(&mut rand)) else {
+                continue;
+            };
+            if !sample.specular {
+                let eval = material.eval(normal, wo, sample.wi);
+                assert!((eval / sample.pdf - sample.weight).len() < 1e-6);
+            }
+            total = total + sample.weight;
+        }
+        total / n as f64
+    }
+
+    #[test]
+    fn samples_match_eval() {
+        let white = Material::principled(Principled::default());
+        let albedo = check_samples(&white);
+        assert!(albedo.x > 0.8 && albedo.x < 1.1);
+
+        let varnished_metal = Material {
+            roughness: 0.4,
+            ..Material::principled(Principled {
+                metallic: 1.0,
+                clearcoat: 1.0,
+                clearcoat_gloss: 0.5,
+                ..Default::default()
+            })
+        };
+        let albedo = check_samples(&varnished_metal);
+        assert!(albedo.x > 0.8 && albedo.x < 1.3);
+
+        let cloth = Material {
+            color: Vec3::new(200.0, 50.0, 50.0),
+            roughness: 0.9,
+            ..Material::principled(Principled {
+                sheen: 1.0,
+                specular: 0.2,
+                ..Default::default()
+            })
+        };
+        let albedo = check_samples(&cloth);
+        assert!(albedo.x > albedo.y);
+    }
+
+    #[test]
+    fn metals_reflect_their_color() {
+        let gold = Material {
+            color: Vec3::new(255.0, 200.0, 50.0),
+            roughness: 0.3,
+            ..Material::principled(Principled {
+                metallic: 1.0,
+                ..Default::default()
+            })
+        };
+        let normal = UnitVec3::new(0.0, 0.0, 1.0);
+        let eval = gold.eval(normal, normal, normal);
+        assert!(eval.x > eval.y && eval.y > eval.z);
+    }
+
+    #[test]
+    fn transmission_refracts() {
+        let glass = Material {
+            refractive_index: 1.5,
+            roughness: 0.0,
+            ..Material::principled(Principled {
+                transmission: 1.0,
+                ..Default::default()
+            })
+        };
+        let normal = UnitVec3::new(0.0, 0.0, 1.0);
+        let sample = glass.sample(normal, normal, true, (0.1, 0.5)).unwrap();
+        assert!(sample.specular);
+        assert_eq!(sample.wi, UnitVec3::new(0.0, 0.0, -1.0));
+        check_samples(&glass);
+    }
+
+    #[test]
+    fn clearcoat_sampling_matches_distribution() {
+        // gtr1(h.z) * h.z integrates to one over the hemisphere
+        let alpha = 0.05;
+        let mut rand = random::default(42);
+        let n = 100000;
+        let integral = (0..n)
+            .map(|_| {
+                let h = sampling::uniform_sphere(uniform(&mut rand));
+                gtr1(h.z, alpha) * h.z.max(0.0) * 4.0 * PI
+            })
+            .sum::<f64>()
+            / n as f64;
+        assert!((integral - 1.0).abs() < 0.05);
+        assert!(sample_gtr1(alpha, (0.5, 0.5)).z > 0.9);
+    }
+
+    #[test]
+    fn sampling_the_edge_without_clearcoat_is_finite() {
+        let white = Material::principled(Principled::default());
+        let normal = UnitVec3::new(0.0, 0.0, 1.0);
+        let wo = UnitVec3::new(0.4, -0.2, 1.0);
+        if let Some(sample) = white.sample(normal, wo, true, (1.0, 0.5)) {
+            assert!(sample.weight.x.is_finite() && sample.pdf.is_finite());
+            assert!(sample.wi.x.is_finite() && sample.wi.z.is_finite());
+        }
+    }
+}
@@ -0,0 +1,458 @@
+//! Wavefront OBJ and MTL import.
+//! http://paulbourke.net/dataformats/obj/
+//! http://paulbourke.net/dataformats/mtl/
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::material::{Material, MaterialKind};
+use crate::scene::mesh::Mesh;
+use crate::vec3::{Pnt3, UnitVec3, Vec3};
+
+/// Loads all groups of an OBJ file as meshes.
+/// Material libraries referenced by `mtllib` are resolved relative to the OBJ file.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<Mesh>> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse(&source, path.parent()).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Loads all materials of an MTL file, keyed by name.
+pub fn load_mtl(path: impl AsRef<Path>) -> Result<HashMap<String, Material>> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_mtl(&source).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Parses OBJ source into one mesh per group and material.
+/// `mtllib` statements are resolved relative to `base_dir`. Without one they are ignored
+/// and `usemtl` falls back to the default material.
+pub fn parse(source: &str, base_dir: Option<&Path>) -> Result<Vec<Mesh>> {
+    let mut positions: Vec<Pnt3> = Vec::new();
+    let mut normals: Vec<UnitVec3> = Vec::new();
+    let mut uvs: Vec<(f64, f64)> = Vec::new();
+    let mut materials: HashMap<String, Material> = HashMap::new();
+
+    let mut meshes = Vec::new();
+    let mut group = MeshBuilder::new(String::new(), Material::default());
+
+    for (line_number, line) in source.lines().enumerate() {
+        let line_number = line_number + 1;
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => continue,
+        };
+        let args = tokens.collect::<Vec<&str>>();
+        let result = match keyword {
+            "v" => parse_floats::<3>(&args, 4).map(|[x, y, z]| positions.push(Vec3::new(x, y, z))),
+            "vn" => parse_floats::<3>(&args, 3).and_then(|[x, y, z]| {
+                let normal = Vec3::new(x, y, z)
+                    .normalize()
+                    .map_err(|_| anyhow!("Normal must not be zero"))?;
+                normals.push(normal);
+                Ok(())
+            }),
+            "vt" => parse_floats::<1>(&args, 3).map(|[u]| {
+                let v = args.get(1).and_then(|v| v.parse().ok()).unwrap_or(0.0);
+                uvs.push((u, v));
+            }),
+            "f" => group.add_face(&args, &positions, &normals, &uvs),
+            "g" | "o" => {
+                let name = args.join(" ");
+                let material = group.material.clone();
+                let previous = std::mem::replace(&mut group, MeshBuilder::new(name, material));
+                meshes.extend(previous.build(&positions, &normals, &uvs));
+                Ok(())
+            }
+            "usemtl" => match materials
+                .get(&args.join(" "))
+                .cloned()
+                .or_else(|| base_dir.is_none().then(Material::default))
+            {
+                Some(material) => {
+                    let name = group.name.clone();
+                    let previous = std::mem::replace(&mut group, MeshBuilder::new(name, material));
+                    meshes.extend(previous.build(&positions, &normals, &uvs));
+                    Ok(())
+                }
+                None => Err(anyhow!("Unknown material `{}`", args.join(" "))),
+            },
+            "mtllib" => match base_dir {
+                Some(base_dir) => args.iter().try_for_each(|file| {
+                    materials.extend(load_mtl(base_dir.join(file))?);
+                    Ok(())
+                }),
+                None => Ok(()),
+            },
+            // Smoothing groups, lines, points and free-form geometry are not supported
+            _ => Ok(()),
+        };
+        result.with_context(|| format!("line {}: `{}`", line_number, line.trim()))?;
+    }
+    meshes.extend(group.build(&positions, &normals, &uvs));
+    Ok(meshes)
+}
+
+/// Parses MTL source into materials, keyed by name.
+///
+/// Mapping onto [`Material`]:
+/// - `Kd` becomes the color
+/// - `Ns` becomes the roughness, a black `Ks` makes the surface fully rough
+/// - `Ni` becomes the refractive index
+/// - `d` and `Tr` are ignored, a dissolve is an opacity and not the absorption per distance
+///   of a dielectric, which stays clear
+/// - `illum` 4, 6, 7 and 9 (refraction) make the material a dielectric
+/// - `Ke` becomes the emission, turning meshes using the material into area lights
+pub fn parse_mtl(source: &str) -> Result<HashMap<String, Material>> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, Material, Option<Vec3>)> = None;
+
+    for (line_number, line) in source.lines().enumerate() {
+        let line_number = line_number + 1;
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => continue,
+        };
+        let args = tokens.collect::<Vec<&str>>();
+        let result = match (keyword, current.as_mut()) {
+            ("newmtl", _) => {
+                if let Some(finished) = current.take() {
+                    insert_material(&mut materials, finished);
+                }
+                if args.is_empty() {
+                    Err(anyhow!("Missing material name"))
+                } else {
+                    current = Some((args.join(" "), Material::default(), None));
+                    Ok(())
+                }
+            }
+            ("Kd", Some((_, material, _))) => parse_floats::<3>(&args, 3).map(|[r, g, b]| {
+                material.color = Vec3::new(r, g, b) * 255.0;
+                material.albedo = 1.0;
+            }),
+            ("illum", Some((_, material, _))) => parse_floats::<1>(&args, 1).map(|[model]| {
+                if [4.0, 6.0, 7.0, 9.0].contains(&model) {
+                    material.kind = MaterialKind::Dielectric;
+                }
+            }),
+            ("Ke", Some((_, material, _))) => {
+                parse_floats::<3>(&args, 3).map(|[r, g, b]| material.emission = Vec3::new(r, g, b))
+            }
+            ("Ks", Some((_, _, specular))) => {
+                parse_floats::<3>(&args, 3).map(|[r, g, b]| *specular = Some(Vec3::new(r, g, b)))
+            }
+            ("Ns", Some((_, material, _))) => parse_floats::<1>(&args, 1)
+                .map(|[exponent]| material.roughness = roughness_from_exponent(exponent)),
+            ("Ni", Some((_, material, _))) => {
+                parse_floats::<1>(&args, 1).map(|[ior]| material.refractive_index = ior)
+            }
+            ("Kd" | "Ke" | "Ks" | "Ns" | "Ni", None) => {
+                Err(anyhow!("`{}` before any `newmtl`", keyword))
+            }
+            // Dissolve, ambient color and texture maps are not supported
+            _ => Ok(()),
+        };
+        result.with_context(|| format!("line {}: `{}`", line_number, line.trim()))?;
+    }
+    if let Some(finished) = current.take() {
+        insert_material(&mut materials, finished);
+    }
+    Ok(materials)
+}
+
+fn insert_material(
+    materials: &mut HashMap<String, Material>,
+    (name, mut material, specular): (String, Material, Option<Vec3>),
+) {
+    if specular.is_some_and(|ks| ks.x.max(ks.y).max(ks.z) <= 0.0) {
+        material.roughness = 1.0;
+    }
+    materials.insert(name, material);
+}
+
+/// Converts a Phong specular exponent into a perceptual roughness between 0.0 and 1.0,
+/// the square root of the microfacet alpha.
+/// https://simonstechblog.blogspot.com/2011/12/microfacet-brdf.html
+fn roughness_from_exponent(exponent: f64) -> f64 {
+    (2.0 / (exponent.max(0.0) + 2.0)).sqrt().sqrt()
+}
+
+/// Parses between `N` and `max` arguments as floats and returns the first `N`.
+fn parse_floats<const N: usize>(args: &[&str], max: usize) -> Result<[f64; N]> {
+    if args.len() < N || args.len() > max {
+        bail!("Expected {} to {} numbers, got {}", N, max, args.len());
+    }
+    let mut values = [0.0; N];
+    for (i, arg) in args.iter().enumerate() {
+        let value = arg
+            .parse()
+            .map_err(|_| anyhow!("`{}` is not a number", arg))?;
+        if i < N {
+            values[i] = value;
+        }
+    }
+    Ok(values)
+}
+
+/// Resolves a 1-based, possibly negative (relative) OBJ index into a 0-based index.
+fn resolve_index(index: &str, len: usize) -> Result<usize> {
+    let index: i64 = index
+        .parse()
+        .map_err(|_| anyhow!("`{}` is not an index", index))?;
+    let resolved = if index < 0 {
+        len as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= len as i64 {
+        bail!("Index {} out of range, {} elements defined", index, len);
+    }
+    Ok(resolved as usize)
+}
+
+/// Collects the faces of one group/material combination.
+/// OBJ indexes positions, normals and UVs separately, so every distinct
+/// combination becomes one vertex of the mesh.
+struct MeshBuilder {
+    name: String,
+    material: Material,
+    vertices: HashMap<(usize, Option<usize>, Option<usize>), usize>,
+    keys: Vec<(usize, Option<usize>, Option<usize>)>,
+    indices: Vec<[usize; 3]>,
+}
+
+impl MeshBuilder {
+    fn new(name: String, material: Material) -> MeshBuilder {
+        MeshBuilder {
+            name,
+            material,
+            vertices: HashMap::new(),
+            keys: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn add_face(
+        &mut self,
+        args: &[&str],
+        positions: &[Pnt3],
+        normals: &[UnitVec3],
+        uvs: &[(f64, f64)],
+    ) -> Result<()> {
+        if args.len() < 3 {
+            bail!("A face needs at least 3 vertices, got {}", args.len());
+        }
+        let face = args
+            .iter()
+            .map(|vertex| {
+                let mut parts = vertex.split('/');
+                let position = resolve_index(parts.next().unwrap_or(""), positions.len())?;
+                let uv = match parts.next() {
+                    Some("") | None => None,
+                    Some(uv) => Some(resolve_index(uv, uvs.len())?),
+                };
+                let normal = match parts.next() {
+                    Some("") | None => None,
+                    Some(normal) => Some(resolve_index(normal, normals.len())?),
+                };
+                if parts.next().is_some() {
+                    bail!("Malformed face vertex `{}`", vertex);
+                }
+                let key = (position, uv, normal);
+                let next_index = self.keys.len();
+                let index = *self.vertices.entry(key).or_insert(next_index);
+                if index == next_index {
+                    self.keys.push(key);
+                }
+                Ok(index)
+            })
+            .collect::<Result<Vec<usize>>>()?;
+
+        // Triangulate polygons as a fan around the first vertex
+        for i in 1..face.len() - 1 {
+            self.indices.push([face[0], face[i], face[i + 1]]);
+        }
+        Ok(())
+    }
+
+    /// Returns `None` for groups without faces.
+    /// Normals and UVs are dropped unless every vertex of the group has them.
+    fn build(self, positions: &[Pnt3], normals: &[UnitVec3], uvs: &[(f64, f64)]) -> Option<Mesh> {
+        if self.indices.is_empty() {
+            return None;
+        }
+        let vertices = self
+            .keys
+            .iter()
+            .map(|(p, _, _)| positions[*p])
+            .collect::<Vec<Pnt3>>();
+        let normals = self
+            .keys
+            .iter()
+            .map(|(_, _, n)| n.map(|n| normals[n]))
+            .collect::<Option<Vec<UnitVec3>>>()
+            .unwrap_or_default();
+        let uvs = self
+            .keys
+            .iter()
+            .map(|(_, uv, _)| uv.map(|uv| uvs[uv]))
+            .collect::<Option<Vec<(f64, f64)>>>()
+            .unwrap_or_default();
+        let mut mesh = Mesh::new(vertices, self.indices);
+        mesh.name = self.name;
+        mesh.normals = normals;
+        mesh.uvs = uvs;
+        mesh.material = self.material;
+        Some(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CUBE_CORNER: &str = "
+# two faces sharing an edge
+o corner
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v 0 0 1
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+vn 0 1 0
+f 1/1/1 2/2/1 3/3/1 4/4/1
+g side
+f -1//2 -4//2 -5//2
+";
+
+    #[test]
+    fn parse_groups() {
+        let meshes = parse(CUBE_CORNER, None).unwrap();
+        assert_eq!(meshes.len(), 2);
+
+        let front = &meshes[0];
+        assert_eq!(front.name, "corner");
+        assert_eq!(front.vertices.len(), 4);
+        assert_eq!(front.indices, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(front.normals, vec![UnitVec3::new(0.0, 0.0, 1.0); 4]);
+        assert_eq!(
+            front.uvs,
+            vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+        );
+
+        let side = &meshes[1];
+        assert_eq!(side.name, "side");
+        assert_eq!(
+            side.vertices,
+            vec![
+                Pnt3::new(0.0, 0.0, 1.0),
+                Pnt3::new(1.0, 0.0, 0.0),
+                Pnt3::new(0.0, 0.0, 0.0)
+            ]
+        );
+        assert_eq!(side.normals.len(), 3);
+        assert!(side.uvs.is_empty());
+    }
+
+    #[test]
+    fn shared_vertices_are_deduplicated() {
+        let meshes = parse(
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf 1 2 3\nf 3 2 4\n",
+            None,
+        )
+        .unwrap();
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].vertices.len(), 4);
+        assert_eq!(meshes[0].indices, vec![[0, 1, 2], [2, 1, 3]]);
+        assert!(meshes[0].normals.is_empty());
+    }
+
+    #[test]
+    fn parse_errors_report_line_numbers() {
+        let error = parse("v 0 0 0\nv 1 0 zero\n", None).unwrap_err();
+        assert!(format!("{:#}", error).contains("line 2"));
+
+        let error = parse("v 0 0 0\nv 1 0 0\n\nf 1 2 3\n", None).unwrap_err();
+        assert!(format!("{:#}", error).contains("line 4"));
+
+        let error = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1 2/1 3/1\n", None).unwrap_err();
+        assert!(format!("{:#}", error).contains("line 4"));
+
+        let error = parse_mtl("newmtl a\nKd 1 1\n").unwrap_err();
+        assert!(format!("{:#}", error).contains("line 2"));
+
+        let error = parse_mtl("Kd 1 1 1\n").unwrap_err();
+        assert!(format!("{:#}", error).contains("line 1"));
+    }
+
+    #[test]
+    fn parse_materials() {
+        let materials = parse_mtl(
+            "
+newmtl red
+Kd 1.0 0.0 0.0
+Ks 0.5 0.5 0.5
+Ns 30
+Ni 1.5
+d 0.75
+Tr 0.25
+illum 7
+
+newmtl matte
+Kd 0.5 0.5 0.5
+Ks 0 0 0
+Ns 500
+Ke 2 2 1.5
+",
+        )
+        .unwrap();
+        assert_eq!(materials.len(), 2);
+
+        let red = &materials["red"];
+        assert_eq!(red.color, Vec3::new(255.0, 0.0, 0.0));
+        assert_eq!(red.roughness, 0.5);
+        assert_eq!(red.refractive_index, 1.5);
+        // `d` is an opacity, not an absorption coefficient
+        assert_eq!(red.absorption_coefficient, 0.0);
+        assert_eq!(red.kind, MaterialKind::Dielectric);
+
+        let matte = &materials["matte"];
+        assert_eq!(matte.color, Vec3::new(127.5, 127.5, 127.5));
+        assert_eq!(matte.roughness, 1.0);
+        assert_eq!(matte.kind, MaterialKind::Diffuse);
+        assert_eq!(matte.emission, Vec3::new(2.0, 2.0, 1.5));
+        assert!(!red.is_emissive());
+    }
+
+    #[test]
+    fn load_with_material_library() {
+        let dir = std::env::temp_dir().join("raytracer_obj_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("scene.mtl"), "newmtl glass\nKd 1 1 1\nNi 1.5\n").unwrap();
+        std::fs::write(
+            dir.join("scene.obj"),
+            "mtllib scene.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nusemtl glass\nf 3 2 1\n",
+        )
+        .unwrap();
+
+        let meshes = load(dir.join("scene.obj")).unwrap();
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[0].material, Material::default());
+        assert_eq!(meshes[1].material.refractive_index, 1.5);
+
+        std::fs::write(dir.join("broken.obj"), "mtllib scene.mtl\nusemtl metal\n").unwrap();
+        let error = load(dir.join("broken.obj")).unwrap_err();
+        assert!(format!("{:#}", error).contains("line 2"));
+    }
+}
@@ -8,12 +8,46 @@ use crate::vec3::*;
 pub struct Ray {
     pub origin: Pnt3,
     pub dir: UnitVec3,
+    /// When the ray is traced during the exposure, see [`crate::camera::Camera::shutter`].
+    pub time: f64,
+    /// Width of the region the ray stands for at its origin, e.g. a pixel of the film.
+    /// Textures are averaged over it, 0.0 looks them up at full resolution.
+    pub width: f64,
+    /// Growth of `width` per unit of distance along the ray,
+    /// the angle between the rays through neighboring pixels.
+    pub spread: f64,
 }
 
 impl Ray {
     #[ensures(ret.origin == origin)]
     pub fn new(origin: Pnt3, dir: UnitVec3) -> Ray {
-        Ray { origin, dir }
+        Ray {
+            origin,
+            dir,
+            time: 0.0,
+            width: 0.0,
+            spread: 0.0,
+        }
+    }
+
+    /// Returns the ray traced at `time`.
+    pub fn with_time(mut self, time: f64) -> Ray {
+        self.time = time;
+        self
+    }
+
+    /// Returns the ray standing for a cone `width` wide at its origin,
+    /// widening by `spread` per unit of distance.
+    #[requires(width >= 0.0 && spread >= 0.0)]
+    pub fn with_cone(mut self, width: f64, spread: f64) -> Ray {
+        self.width = width;
+        self.spread = spread;
+        self
+    }
+
+    /// Width of the ray's cone at the distance `t`.
+    pub fn footprint(&self, t: f64) -> f64 {
+        self.width + self.spread * t
     }
 
     #[ensures(ret == self.origin + self.dir * t)]
@@ -69,6 +103,28 @@ pub struct IntersectResult {
     pub normal: UnitVec3,
     /// The material of the surface at the intersection point
     pub material: Material,
+    /// The texture coordinates of the intersection point
+    pub uv: (f64, f64),
+    /// The change of the intersection point along the texture coordinate `u`
+    pub dpdu: Vec3,
+    /// The change of the intersection point along the texture coordinate `v`
+    pub dpdv: Vec3,
+}
+
+impl IntersectResult {
+    /// Width in texture coordinates of a region `width` wide around the intersection point,
+    /// along the texture coordinate that changes the most.
+    pub fn uv_footprint(&self, width: f64) -> f64 {
+        let rate = [self.dpdu.len(), self.dpdv.len()]
+            .into_iter()
+            .filter(|len| *len > 0.0)
+            .fold(f64::INFINITY, f64::min);
+        if rate.is_finite() {
+            width / rate
+        } else {
+            0.0
+        }
+    }
 }
 
 #[cfg(test)]
@@ -118,10 +174,30 @@ mod tests {
         let u = 1.5; // out of bounds
         let v = 0.75;
 
-        // Depending on your implementation, this might panic, return an error, or clamp the value to the interval.
-        // Adjust the test accordingly.
+        // Points outside of the intervals are not clamped
         let result = bounded_ray.at(t, u, v);
+        assert_eq!(result, Pnt3::new(0.5, 1.5, 0.75));
+    }
 
-        // assert_eq!(result, ???);
+    #[test]
+    fn footprints() {
+        let ray = Ray::new(Pnt3::null(), UnitVec3::new(0.0, 0.0, 1.0)).with_cone(0.5, 0.25);
+        assert_eq!(ray.footprint(0.0), 0.5);
+        assert_eq!(ray.footprint(4.0), 1.5);
+
+        // A texture stretched twice as much along v changes slower there
+        let mut hit = IntersectResult {
+            t: 4.0,
+            normal: UnitVec3::new(0.0, 0.0, -1.0),
+            material: Material::default(),
+            uv: (0.0, 0.0),
+            dpdu: Vec3::new(2.0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, 4.0, 0.0),
+        };
+        assert_eq!(hit.uv_footprint(1.0), 0.5);
+        hit.dpdu = Vec3::null();
+        assert_eq!(hit.uv_footprint(1.0), 0.25);
+        hit.dpdv = Vec3::null();
+        assert_eq!(hit.uv_footprint(1.0), 0.0);
     }
 }
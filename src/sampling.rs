@@ -0,0 +1,231 @@
+//! Warps uniform random numbers into the distributions used for Monte Carlo integration.
+//! https://pbr-book.org/3ed-2018/Monte_Carlo_Integration/2D_Sampling_with_Multidimensional_Transformations
+
+use std::f64::consts::{FRAC_PI_4, PI};
+
+use contracts::*;
+
+use crate::vec3::{UnitVec3, Vec3};
+
+/// An orthonormal basis with the normal as local z axis.
+/// Local directions have `z == cos(theta)` to the normal.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Frame {
+    pub tangent: UnitVec3,
+    pub bitangent: UnitVec3,
+    pub normal: UnitVec3,
+}
+
+impl Frame {
+    /// Builds a frame around the normal with arbitrary, but continuous, tangents.
+    /// https://graphics.pixar.com/library/OrthonormalB/paper.pdf
+    pub fn from_normal(normal: UnitVec3) -> Frame {
+        let sign = 1.0f64.copysign(normal.z);
+        let a = -1.0 / (sign + normal.z);
+        let b = normal.x * normal.y * a;
+        Frame {
+            tangent: UnitVec3::new_unchecked(
+                1.0 + sign * normal.x * normal.x * a,
+                sign * b,
+                -sign * normal.x,
+            ),
+            bitangent: UnitVec3::new_unchecked(b, sign + normal.y * normal.y * a, -normal.y),
+            normal,
+        }
+    }
+
+    pub fn to_world(&self, local: Vec3) -> Vec3 {
+        self.tangent * local.x + self.bitangent * local.y + self.normal * local.z
+    }
+
+    pub fn to_local(&self, world: Vec3) -> Vec3 {
+        Vec3::new(
+            self.tangent.dot(world),
+            self.bitangent.dot(world),
+            self.normal.dot(world),
+        )
+    }
+}
+
+/// Maps the unit square onto the unit disk, preserving relative areas.
+/// https://pbr-book.org/3ed-2018/Monte_Carlo_Integration/2D_Sampling_with_Multidimensional_Transformations#ConcentricSampleDisk
+pub fn concentric_disk(u: (f64, f64)) -> (f64, f64) {
+    let x = 2.0 * u.0 - 1.0;
+    let y = 2.0 * u.1 - 1.0;
+    if x == 0.0 && y == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (r, theta) = if x.abs() > y.abs() {
+        (x, FRAC_PI_4 * (y / x))
+    } else {
+        (y, 2.0 * FRAC_PI_4 - FRAC_PI_4 * (x / y))
+    };
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// Samples a local direction in the upper hemisphere with a density proportional to `cos(theta)`.
+pub fn cosine_hemisphere(u: (f64, f64)) -> Vec3 {
+    let (x, y) = concentric_disk(u);
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+    Vec3::new(x, y, z)
+}
+
+/// Density of [`cosine_hemisphere`] per solid angle.
+pub fn cosine_hemisphere_pdf(cos_theta: f64) -> f64 {
+    cos_theta.max(0.0) / PI
+}
+
+/// Samples a direction uniformly on the unit sphere.
+pub fn uniform_sphere(u: (f64, f64)) -> Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// Samples barycentric coordinates `(u, v)` uniformly distributed over a triangle's area,
+/// `u` being the weight of the second and `v` of the third vertex.
+/// https://pbr-book.org/3ed-2018/Monte_Carlo_Integration/2D_Sampling_with_Multidimensional_Transformations#SamplingaTriangle
+pub fn uniform_triangle(u: (f64, f64)) -> (f64, f64) {
+    let su0 = u.0.sqrt();
+    (su0 * (1.0 - u.1), su0 * u.1)
+}
+
+/// Samples a point uniformly distributed over a regular polygon inscribed in the unit circle,
+/// with its first corner on the positive x axis. Used for the blades of camera apertures.
+#[requires(sides >= 3)]
+pub fn uniform_polygon(sides: u32, u: (f64, f64)) -> (f64, f64) {
+    // Every side spans an equally large triangle with the center
+    let scaled = u.0 * sides as f64;
+    let side = (scaled.floor() as u32).min(sides - 1);
+    let (b, c) = uniform_triangle((scaled - side as f64, u.1));
+    let corner = |i: u32| {
+        let angle = 2.0 * PI * i as f64 / sides as f64;
+        (angle.cos(), angle.sin())
+    };
+    let (p1, p2) = (corner(side), corner(side + 1));
+    (b * p1.0 + c * p2.0, b * p1.1 + c * p2.1)
+}
+
+/// Picks an index from a cumulative distribution, e.g. of triangle areas.
+/// Returns the index and `u` remapped to `[0, 1)` within the picked entry, to be reused.
+pub fn sample_cdf(cdf: &[f64], u: f64) -> (usize, f64) {
+    let total = cdf[cdf.len() - 1];
+    let target = u * total;
+    let index = cdf.partition_point(|sum| *sum <= target).min(cdf.len() - 1);
+    let lower = if index == 0 { 0.0 } else { cdf[index - 1] };
+    let width = cdf[index] - lower;
+    let remapped = if width > 0.0 {
+        ((target - lower) / width).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (index, remapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_is_orthonormal() {
+        for normal in [
+            UnitVec3::new(0.0, 0.0, 1.0),
+            UnitVec3::new(0.0, 0.0, -1.0),
+            UnitVec3::new(1.0, 2.0, 3.0),
+            UnitVec3::new(-0.3, 0.1, -0.9),
+        ] {
+            let frame = Frame::from_normal(normal);
+            assert!(frame.tangent.dot(frame.bitangent).abs() < 1e-12);
+            assert!(frame.tangent.dot(frame.normal).abs() < 1e-12);
+            assert!(frame.bitangent.dot(frame.normal).abs() < 1e-12);
+            assert!((frame.tangent.cross(frame.bitangent) - Vec3::from(normal)).len() < 1e-12);
+
+            let world = Vec3::new(0.2, -0.4, 0.7);
+            assert!((frame.to_world(frame.to_local(world)) - world).len() < 1e-12);
+            assert!((frame.to_local(Vec3::from(normal)) - Vec3::new(0.0, 0.0, 1.0)).len() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn concentric_disk_stays_in_disk() {
+        assert_eq!(concentric_disk((0.5, 0.5)), (0.0, 0.0));
+        let (x, y) = concentric_disk((1.0, 0.5));
+        assert!((x - 1.0).abs() < 1e-12 && y.abs() < 1e-12);
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let (x, y) = concentric_disk((i as f64 / 10.0, j as f64 / 10.0));
+                assert!(x * x + y * y <= 1.0 + 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn cosine_hemisphere_mean() {
+        // The mean of cos(theta) under a cosine weighted density is 2/3
+        let mut rand = random::default(1);
+        let n = 10000;
+        let mean = (0..n)
+            .map(|_| {
+                let u = (
+                    random::Source::read_f64(&mut rand),
+                    random::Source::read_f64(&mut rand),
+                );
+                let dir = cosine_hemisphere(u);
+                assert!(dir.z >= 0.0);
+                assert!((dir.len() - 1.0).abs() < 1e-9);
+                dir.z
+            })
+            .sum::<f64>()
+            / n as f64;
+        assert!((mean - 2.0 / 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn uniform_sphere_is_on_the_sphere() {
+        assert_eq!(uniform_sphere((0.0, 0.0)), Vec3::new(0.0, 0.0, 1.0));
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let dir = uniform_sphere((i as f64 / 10.0, j as f64 / 10.0));
+                assert!((dir.len() - 1.0).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_triangle_stays_inside() {
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let (u, v) = uniform_triangle((i as f64 / 10.0, j as f64 / 10.0));
+                assert!(u >= 0.0 && v >= 0.0 && u + v <= 1.0 + 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_polygon_stays_inside() {
+        // Points of a square with corners on the axes satisfy |x| + |y| <= 1
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let (x, y) = uniform_polygon(4, (i as f64 / 10.0, j as f64 / 10.0));
+                assert!(x.abs() + y.abs() <= 1.0 + 1e-12, "{} {}", x, y);
+            }
+        }
+        let (x, y) = uniform_polygon(6, (0.0, 0.0));
+        assert_eq!((x, y), (0.0, 0.0));
+        // Every side gets the same share of the samples
+        let (x, y) = uniform_polygon(4, (0.3, 0.5));
+        assert!(x < 0.0 && y > 0.0);
+    }
+
+    #[test]
+    fn cdf_sampling() {
+        let cdf = [1.0, 1.0, 4.0];
+        assert_eq!(sample_cdf(&cdf, 0.0), (0, 0.0));
+        assert_eq!(sample_cdf(&cdf, 0.125), (0, 0.5));
+        // The empty entry is never picked
+        assert_eq!(sample_cdf(&cdf, 0.25), (2, 0.0));
+        assert_eq!(sample_cdf(&cdf, 0.625), (2, 0.5));
+        assert_eq!(sample_cdf(&cdf, 1.0), (2, 1.0));
+    }
+}
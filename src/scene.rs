@@ -1,6 +1,302 @@
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::light::Light;
+use crate::material::Material;
 use crate::ray::*;
+use crate::vec3::{Pnt3, UnitVec3, Vec3};
 
-// Using Macro, modify struct to add a container for each type of hitable from folder scene/*.rs
-//
-#[add_containers_from_files("src/scene/*.rs")]
-struct Scene {}
+pub mod cube;
+pub mod line;
+pub mod mesh;
+pub mod moving;
+pub mod plane;
+pub mod quad;
+pub mod sphere;
+pub mod triangle;
+
+/// An object rays can be intersected with.
+/// Implement this for custom primitives to add them to a [`Scene`].
+pub trait Hittable: Send + Sync {
+    /// The closest intersection of the ray with the object, if any.
+    fn intersect(&self, ray: &Ray) -> Option<IntersectResult>;
+
+    /// Bounds of the object, [`Aabb::infinite`] for unbounded objects like planes.
+    fn bounding_box(&self) -> Aabb;
+
+    /// The material of the object's surface.
+    fn material(&self) -> &Material;
+
+    /// Samples a point uniformly distributed over the surface as it is at `time`.
+    /// This is how emissive objects are sampled as area lights,
+    /// emissive objects returning `None` are only seen directly and light no other surface.
+    fn sample_surface(&self, _u: (f64, f64), _time: f64) -> Option<SurfaceSample> {
+        None
+    }
+}
+
+/// A point sampled on the surface of an object.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SurfaceSample {
+    pub point: Pnt3,
+    pub normal: UnitVec3,
+    /// Texture coordinates of `point`, as a hit there would report them.
+    pub uv: (f64, f64),
+    /// Probability density of having sampled `point`, per unit area.
+    pub pdf: f64,
+}
+
+/// A collection of objects of any [`Hittable`] type, lit by analytic [`Light`]s,
+/// emissive objects and the background.
+pub struct Scene {
+    objects: Vec<Box<dyn Hittable>>,
+    bvh: Option<Bvh>,
+    /// Indices into `objects` of the primitives in `bvh`.
+    bounded: Vec<usize>,
+    /// Indices into `objects` that are tested against every ray.
+    unbounded: Vec<usize>,
+    /// Indices into `objects` of the objects with an emissive material.
+    emitters: Vec<usize>,
+    lights: Vec<Box<dyn Light>>,
+    /// Radiance arriving from every direction no object is hit in.
+    background: Vec3,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self {
+            objects: Vec::new(),
+            bvh: None,
+            bounded: Vec::new(),
+            unbounded: Vec::new(),
+            emitters: Vec::new(),
+            lights: Vec::new(),
+            background: Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    pub fn add(&mut self, object: impl Hittable + 'static) {
+        self.add_boxed(Box::new(object));
+    }
+
+    pub fn add_boxed(&mut self, object: Box<dyn Hittable>) {
+        if object.material().is_emissive() {
+            self.emitters.push(self.objects.len());
+        }
+        self.objects.push(object);
+        self.bvh = None;
+    }
+
+    pub fn add_light(&mut self, light: impl Light + 'static) {
+        self.add_light_boxed(Box::new(light));
+    }
+
+    pub fn add_light_boxed(&mut self, light: Box<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    pub fn lights(&self) -> &[Box<dyn Light>] {
+        &self.lights
+    }
+
+    pub fn background(&self) -> Vec3 {
+        self.background
+    }
+
+    pub fn set_background(&mut self, radiance: Vec3) {
+        self.background = radiance;
+    }
+
+    pub fn objects(&self) -> &[Box<dyn Hittable>] {
+        &self.objects
+    }
+
+    /// Indices into [`Scene::objects`] of the objects emitting light.
+    pub fn emitters(&self) -> &[usize] {
+        &self.emitters
+    }
+
+    /// Builds the bounding volume hierarchy over all bounded objects.
+    /// Adding objects discards it, until it is built again `intersect` tests every object.
+    pub fn build_bvh(&mut self) {
+        let mut bounds = Vec::new();
+        self.bounded.clear();
+        self.unbounded.clear();
+        for (index, object) in self.objects.iter().enumerate() {
+            let aabb = object.bounding_box();
+            if aabb.is_finite() {
+                bounds.push(aabb);
+                self.bounded.push(index);
+            } else {
+                self.unbounded.push(index);
+            }
+        }
+        self.bvh = Some(Bvh::build(&bounds));
+    }
+
+    pub fn intersect(&self, ray: &Ray) -> Option<IntersectResult> {
+        self.intersect_object(ray).map(|(_, hit)| hit)
+    }
+
+    /// The closest intersection together with the index into [`Scene::objects`] of the object hit.
+    pub fn intersect_object(&self, ray: &Ray) -> Option<(usize, IntersectResult)> {
+        let hit = |i: usize| self.objects[i].intersect(ray).map(|hit| (i, hit));
+        let bvh = match &self.bvh {
+            Some(bvh) => bvh,
+            None => return Self::closest((0..self.objects.len()).map(hit)),
+        };
+        let bounded = bvh
+            .closest_hit(ray, |i| hit(self.bounded[i]).map(|hit| (hit.1.t, hit)))
+            .map(|(_, hit)| hit);
+        Self::closest(
+            self.unbounded
+                .iter()
+                .map(|i| hit(*i))
+                .chain(std::iter::once(bounded)),
+        )
+    }
+
+    fn closest(
+        hits: impl Iterator<Item = Option<(usize, IntersectResult)>>,
+    ) -> Option<(usize, IntersectResult)> {
+        hits.flatten()
+            .min_by(|x, y| x.1.t.partial_cmp(&y.1.t).unwrap())
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::{Pnt3, UnitVec3};
+
+    #[test]
+    fn bvh_matches_linear_scan() {
+        let mut scene = Scene::new();
+        let mut rand = random::default(3);
+        for _ in 0..200 {
+            let mid = Pnt3::new(
+                random::Source::read_f64(&mut rand) * 100.0,
+                random::Source::read_f64(&mut rand) * 100.0,
+                random::Source::read_f64(&mut rand) * 100.0,
+            );
+            scene.add(sphere::Sphere::new(mid, 2.0));
+        }
+        scene.add(plane::Plane::new(
+            Pnt3::new(0.0, 0.0, 150.0),
+            UnitVec3::new(0.0, 0.0, -1.0),
+        ));
+        let rays = (0..100)
+            .map(|i| {
+                Ray::new(
+                    Pnt3::new(i as f64, (i * 7 % 100) as f64, -10.0),
+                    UnitVec3::new(0.01 * (i % 5) as f64, -0.01 * (i % 3) as f64, 1.0),
+                )
+            })
+            .collect::<Vec<Ray>>();
+
+        let linear = rays
+            .iter()
+            .map(|ray| scene.intersect(ray))
+            .collect::<Vec<_>>();
+        scene.build_bvh();
+        let accelerated = rays
+            .iter()
+            .map(|ray| scene.intersect(ray))
+            .collect::<Vec<_>>();
+        assert_eq!(linear, accelerated);
+        assert!(accelerated.iter().all(|hit| hit.is_some()));
+    }
+
+    /// A primitive defined outside of this crate's scene module.
+    struct Floor {
+        height: f64,
+    }
+
+    impl Hittable for Floor {
+        fn intersect(&self, ray: &Ray) -> Option<IntersectResult> {
+            let t = (self.height - ray.origin.y) / ray.dir.y;
+            (t > 0.0).then(|| IntersectResult {
+                t,
+                normal: UnitVec3::new(0.0, 1.0, 0.0),
+                material: self.material().clone(),
+                uv: (0.0, 0.0),
+                dpdu: Vec3::new(1.0, 0.0, 0.0),
+                dpdv: Vec3::new(0.0, 0.0, 1.0),
+            })
+        }
+
+        fn bounding_box(&self) -> Aabb {
+            Aabb::infinite()
+        }
+
+        fn material(&self) -> &Material {
+            &Material::DEFAULT
+        }
+    }
+
+    #[test]
+    fn custom_hittable() {
+        let mut scene = Scene::new();
+        scene.add(Floor { height: -1.0 });
+        scene.add(sphere::Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 0.5));
+        scene.build_bvh();
+        assert_eq!(scene.objects().len(), 2);
+
+        let ray = Ray::new(Pnt3::new(0.0, 2.0, 0.0), UnitVec3::new(0.0, -1.0, 0.0));
+        assert_eq!(scene.intersect(&ray).unwrap().t, 1.5);
+
+        let ray = Ray::new(Pnt3::new(3.0, 2.0, 0.0), UnitVec3::new(0.0, -1.0, 0.0));
+        assert_eq!(scene.intersect(&ray).unwrap().t, 3.0);
+    }
+
+    #[test]
+    fn hits_know_their_object() {
+        let mut scene = Scene::new();
+        scene.add(Floor { height: -1.0 });
+        scene.add(sphere::Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 0.5));
+        for bvh in [false, true] {
+            if bvh {
+                scene.build_bvh();
+            }
+            let ray = Ray::new(Pnt3::new(0.0, 2.0, 0.0), UnitVec3::new(0.0, -1.0, 0.0));
+            assert_eq!(scene.intersect_object(&ray).unwrap().0, 1);
+            let ray = Ray::new(Pnt3::new(3.0, 2.0, 0.0), UnitVec3::new(0.0, -1.0, 0.0));
+            assert_eq!(scene.intersect_object(&ray).unwrap().0, 0);
+        }
+    }
+
+    #[test]
+    fn objects_keep_their_material() {
+        let red = Material {
+            color: Vec3::new(255.0, 0.0, 0.0),
+            ..Default::default()
+        };
+        let mut scene = Scene::new();
+        scene.add(sphere::Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 1.0).with_material(red.clone()));
+        scene.add(sphere::Sphere::new(Pnt3::new(5.0, 0.0, 0.0), 1.0));
+
+        let ray = Ray::new(Pnt3::new(0.0, 0.0, -5.0), UnitVec3::new(0.0, 0.0, 1.0));
+        assert_eq!(scene.intersect(&ray).unwrap().material, red);
+        assert_eq!(scene.objects()[0].material(), &red);
+
+        let ray = Ray::new(Pnt3::new(5.0, 0.0, -5.0), UnitVec3::new(0.0, 0.0, 1.0));
+        assert_eq!(scene.intersect(&ray).unwrap().material, Material::default());
+    }
+
+    #[test]
+    fn emitters_are_tracked() {
+        let light = Material {
+            emission: Vec3::new(4.0, 4.0, 4.0),
+            ..Default::default()
+        };
+        let mut scene = Scene::new();
+        scene.add(sphere::Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 1.0));
+        scene.add(sphere::Sphere::new(Pnt3::new(5.0, 0.0, 0.0), 1.0).with_material(light));
+        assert_eq!(scene.emitters(), &[1]);
+    }
+}
@@ -1,22 +1,69 @@
 use contracts::*;
 
-use crate::material;
+use crate::aabb::Aabb;
+use crate::material::Material;
 use crate::ray::{self, IntersectResult};
-use crate::vec3::{Pnt3, UnitVec3, Vec3};
+use crate::scene::Hittable;
+use crate::vec3::{Pnt3, Vec3};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Cube {
     pub p1: Pnt3,
     pub p2: Pnt3,
+    pub material: Material,
 }
 
 impl Cube {
     #[ensures(ret.p1 != ret.p2)]
     pub fn new(p1: Pnt3, p2: Pnt3) -> Cube {
-        Cube { p1, p2 }
+        Cube {
+            p1,
+            p2,
+            material: Default::default(),
+        }
+    }
+
+    /// Returns the cube with its surface made of `material`.
+    pub fn with_material(mut self, material: Material) -> Cube {
+        self.material = material;
+        self
     }
 
-    pub fn intersect(&self, ray: &ray::Ray) -> Option<IntersectResult> {
+    /// Maps each face onto the unit square, along the two axes it spans.
+    /// Returns the texture coordinates with their derivatives.
+    fn face_mapping(&self, p: Pnt3, normal: Vec3) -> ((f64, f64), Vec3, Vec3) {
+        let min = Pnt3::new(
+            self.p1.x.min(self.p2.x),
+            self.p1.y.min(self.p2.y),
+            self.p1.z.min(self.p2.z),
+        );
+        let size = Vec3::new(
+            (self.p1.x - self.p2.x).abs(),
+            (self.p1.y - self.p2.y).abs(),
+            (self.p1.z - self.p2.z).abs(),
+        );
+        let along = |i: usize| ((p[i] - min[i]) / size[i]).clamp(0.0, 1.0);
+        let axis = |i: usize| {
+            let mut d = Vec3::null();
+            d[i] = size[i];
+            d
+        };
+        // The axis the face is perpendicular to, for edges the first one
+        let (u, v) = match (0..3).find(|i| normal[*i] != 0.0) {
+            Some(0) => (2, 1),
+            Some(1) => (0, 2),
+            _ => (0, 1),
+        };
+        ((along(u), along(v)), axis(u), axis(v))
+    }
+}
+
+impl Hittable for Cube {
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(&[self.p1, self.p2])
+    }
+
+    fn intersect(&self, ray: &ray::Ray) -> Option<IntersectResult> {
         let mut t_min = -f64::INFINITY;
         let mut t_max = f64::INFINITY;
         for i in 0..3 {
@@ -32,7 +79,11 @@ impl Cube {
                 return None;
             }
         }
-        let t = t_min.max(0.0).min(t_max.max(0.0));
+        if t_max < 0.0 {
+            return None;
+        }
+        // Rays starting inside the cube hit it where they leave it
+        let t = if t_min >= 0.0 { t_min } else { t_max };
         let p = ray.at(t);
         let mut normal = Vec3::null();
         for i in 0..3 {
@@ -42,24 +93,26 @@ impl Cube {
                 normal[i] = 1.0;
             }
         }
-        let material = material::Material {
-            color: Vec3::new(255.0, 100.0, 100.0),
-            albedo: 0.1,
-            roughness: 0.8,
-            refractive_index: 1.0,
-            absorption_coefficient: 0.0,
-        };
+        let (uv, dpdu, dpdv) = self.face_mapping(p, normal);
         Some(IntersectResult {
             t,
             normal: normal.normalize().unwrap(),
-            material,
+            material: self.material.clone(),
+            uv,
+            dpdu,
+            dpdv,
         })
     }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::vec3::UnitVec3;
 
     #[test]
     fn test_intersect() {
@@ -96,6 +149,8 @@ mod tests {
         // y
         let ray = ray::Ray::new(Pnt3::new(0.5, -1.0, 0.5), UnitVec3::new(0.0, 1.0, 0.0));
         let intersectresult = cube.intersect(&ray).unwrap();
+        let t = intersectresult.t;
+        let normal = intersectresult.normal;
         assert_eq!(t, 1.0);
         assert_eq!(normal, UnitVec3::new(0.0, -1.0, 0.0));
 
@@ -111,7 +166,7 @@ mod tests {
         let intersectresult = cube.intersect(&ray).unwrap();
         let t = intersectresult.t;
         let normal = intersectresult.normal;
-        assert_eq!(t, 1.4142135623730951);
+        assert_eq!(t, std::f64::consts::SQRT_2);
         assert_eq!(normal, UnitVec3::new(-1.0, 0.0, -1.0));
 
         let ray = ray::Ray::new(Pnt3::new(1.0, 0.5, 1.0), UnitVec3::new(-1.0, 0.0, -1.0));
@@ -149,4 +204,15 @@ mod tests {
         let ray = ray::Ray::new(Pnt3::new(0.0, 5.0, 0.0), UnitVec3::new(1.0, 1.0, 1.0));
         assert!(cube.intersect(&ray).is_none());
     }
+
+    #[test]
+    fn texture_coordinates() {
+        let cube = Cube::new(Pnt3::new(0.0, 0.0, 0.0), Pnt3::new(2.0, 2.0, 2.0));
+        let ray = ray::Ray::new(Pnt3::new(0.5, 1.5, -1.0), UnitVec3::new(0.0, 0.0, 1.0));
+        assert_eq!(cube.intersect(&ray).unwrap().uv, (0.25, 0.75));
+        let ray = ray::Ray::new(Pnt3::new(0.5, 3.0, 1.5), UnitVec3::new(0.0, -1.0, 0.0));
+        assert_eq!(cube.intersect(&ray).unwrap().uv, (0.25, 0.75));
+        let ray = ray::Ray::new(Pnt3::new(-1.0, 0.5, 1.5), UnitVec3::new(1.0, 0.0, 0.0));
+        assert_eq!(cube.intersect(&ray).unwrap().uv, (0.75, 0.25));
+    }
 }
@@ -1,12 +1,20 @@
+use crate::aabb::Aabb;
+use crate::material::Material;
 use crate::ray::{self, IntersectResult};
+use crate::scene::cube::Cube;
+use crate::scene::Hittable;
 use crate::vec3::{Pnt3, UnitVec3, Vec3};
 
+/// A segment from `pnt` along `dir` for `length`, drawn as a beam `width` thick.
+/// The beam is the box around the segment grown across the axes it doesn't run along,
+/// exact for lines along the coordinate axes.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Line {
     pub pnt: Pnt3,
     pub dir: UnitVec3,
     pub width: f64,
     pub length: f64,
+    pub material: Material,
 }
 
 impl Line {
@@ -16,40 +24,51 @@ impl Line {
             dir,
             width,
             length,
+            material: Default::default(),
         }
     }
 
-    pub fn intersect(&self, ray: &ray::Ray) -> Option<IntersectResult> {
-        let mut t_min = -f64::INFINITY;
-        let mut t_max = f64::INFINITY;
-        for i in 0..3 {
-            let inv_d = 1.0 / ray.dir[i];
-            let mut t_0 = (self.pnt[i] - ray.origin[i]) * inv_d;
-            let mut t_1 = (self.pnt[i] + self.dir[i] * self.length - ray.origin[i]) * inv_d;
-            if inv_d < 0.0 {
-                std::mem::swap(&mut t_0, &mut t_1);
-            }
-            t_min = t_min.max(t_0);
-            t_max = t_max.min(t_1);
-            if t_max <= t_min {
-                return None;
-            }
-        }
-        let t = t_min.max(0.0).min(t_max.max(0.0));
-        let p = ray.at(t);
-        let mut normal = Vec3::null();
+    /// Returns the line with its surface made of `material`.
+    pub fn with_material(mut self, material: Material) -> Line {
+        self.material = material;
+        self
+    }
+
+    /// The box the line is drawn as.
+    fn beam(&self) -> Cube {
+        let end = self.pnt + self.dir * self.length;
+        let mut min = Pnt3::null();
+        let mut max = Pnt3::null();
         for i in 0..3 {
-            if p[i] - self.pnt[i] < 0.0001 {
-                normal[i] = -1.0;
-                normal[i] = 1.0;
-            }
+            let half = (1.0 - self.dir[i].abs()) * self.width / 2.0;
+            min[i] = self.pnt[i].min(end[i]) - half;
+            max[i] = self.pnt[i].max(end[i]) + half;
         }
+        Cube::new(min, max)
+    }
+}
+
+impl Hittable for Line {
+    fn bounding_box(&self) -> Aabb {
+        self.beam().bounding_box()
+    }
+
+    fn intersect(&self, ray: &ray::Ray) -> Option<IntersectResult> {
+        let hit = self.beam().intersect(ray)?;
+        let p = ray.at(hit.t);
         Some(IntersectResult {
-            t,
-            normal: normal.normalize().unwrap(),
-            material: Default::default(),
+            material: self.material.clone(),
+            uv: ((p - self.pnt).dot(self.dir) / self.length, 0.0),
+            dpdu: self.dir * self.length,
+            // Lines have no extent across
+            dpdv: Vec3::null(),
+            ..hit
         })
     }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
 }
 
 #[cfg(test)]
@@ -71,11 +90,12 @@ mod tests {
         assert_eq!(t, 1.0);
         assert_eq!(normal, UnitVec3::new(0.0, 0.0, -1.0));
 
+        // Starting inside, the beam is hit where the ray leaves it
         let ray = ray::Ray::new(Pnt3::new(0.5, 0.5, 1.0), UnitVec3::new(0.0, 0.0, 1.0));
         let intersectresult = line.intersect(&ray).unwrap();
         let t = intersectresult.t;
         let normal = intersectresult.normal;
-        assert_eq!(t, 1.0);
+        assert_eq!(t, 9999.0);
         assert_eq!(normal, UnitVec3::new(0.0, 0.0, 1.0));
 
         let ray = ray::Ray::new(Pnt3::new(0.5, 0.5, 0.0), UnitVec3::new(0.0, 0.0, 1.0));
@@ -85,18 +105,21 @@ mod tests {
         assert_eq!(t, 0.0);
         assert_eq!(normal, UnitVec3::new(0.0, 0.0, -1.0));
 
-        let ray = ray::Ray::new(Pnt3::new(0.5, 0.5, 0.5), UnitVec3::new(0.0, 0.0, 1.0));
+        let ray = ray::Ray::new(Pnt3::new(0.5, 0.5, -0.5), UnitVec3::new(0.0, 0.0, 1.0));
         let intersectresult = line.intersect(&ray).unwrap();
         let t = intersectresult.t;
         let normal = intersectresult.normal;
         assert_eq!(t, 0.5);
         assert_eq!(normal, UnitVec3::new(0.0, 0.0, -1.0));
+        assert_eq!(intersectresult.uv, (0.0, 0.0));
 
-        let ray = ray::Ray::new(Pnt3::new(0.5, 0.5, -0.5), UnitVec3::new(0.0, 0.0, 1.0));
+        // From the side, the beam is `width` thick
+        let ray = ray::Ray::new(Pnt3::new(-50.0, 0.0, 10.0), UnitVec3::new(1.0, 0.0, 0.0));
         let intersectresult = line.intersect(&ray).unwrap();
-        let t = intersectresult.t;
-        let normal = intersectresult.normal;
-        assert_eq!(t, -0.5);
-        assert_eq!(normal, UnitVec3::new(0.0, 0.0, -1.0));
+        assert_eq!(intersectresult.t, 25.0);
+        assert_eq!(intersectresult.normal, UnitVec3::new(-1.0, 0.0, 0.0));
+        assert_eq!(intersectresult.uv, (0.001, 0.0));
+        let ray = ray::Ray::new(Pnt3::new(-50.0, 30.0, 10.0), UnitVec3::new(1.0, 0.0, 0.0));
+        assert!(line.intersect(&ray).is_none());
     }
 }
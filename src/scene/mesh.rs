@@ -0,0 +1,353 @@
+use contracts::*;
+
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::material::Material;
+use crate::ray::{self, IntersectResult};
+use crate::sampling;
+use crate::scene::triangle::{self, Triangle};
+use crate::scene::{Hittable, SurfaceSample};
+use crate::vec3::{Pnt3, UnitVec3};
+
+/// An indexed triangle mesh.
+/// Every entry of `indices` references three vertices of a triangle.
+///
+/// The triangles are kept in a [`Bvh`] built on construction.
+/// Call [`Mesh::rebuild_bvh`] after changing `vertices` or `indices`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Mesh {
+    /// Name of the mesh, e.g. the group it was imported from.
+    pub name: String,
+    pub vertices: Vec<Pnt3>,
+    /// Per-vertex normals, parallel to `vertices`.
+    /// Empty for flat shaded meshes that use the face normal of each triangle.
+    pub normals: Vec<UnitVec3>,
+    /// Per-vertex texture coordinates, parallel to `vertices`.
+    /// Empty if the mesh has no texture coordinates.
+    pub uvs: Vec<(f64, f64)>,
+    pub indices: Vec<[usize; 3]>,
+    pub material: Material,
+    bvh: Bvh,
+    /// Running sum of the triangle areas, to sample points uniformly over the surface.
+    area_cdf: Vec<f64>,
+}
+
+impl Mesh {
+    #[requires(indices.iter().flatten().all(|i| *i < vertices.len()))]
+    pub fn new(vertices: Vec<Pnt3>, indices: Vec<[usize; 3]>) -> Mesh {
+        let bvh = Self::build_bvh(&vertices, &indices);
+        let area_cdf = Self::build_area_cdf(&vertices, &indices);
+        Mesh {
+            name: String::new(),
+            vertices,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            bvh,
+            area_cdf,
+            indices,
+            material: Default::default(),
+        }
+    }
+
+    #[requires(normals.len() == vertices.len())]
+    #[requires(indices.iter().flatten().all(|i| *i < vertices.len()))]
+    pub fn with_normals(
+        vertices: Vec<Pnt3>,
+        normals: Vec<UnitVec3>,
+        indices: Vec<[usize; 3]>,
+    ) -> Mesh {
+        let bvh = Self::build_bvh(&vertices, &indices);
+        let area_cdf = Self::build_area_cdf(&vertices, &indices);
+        Mesh {
+            name: String::new(),
+            vertices,
+            normals,
+            uvs: Vec::new(),
+            bvh,
+            area_cdf,
+            indices,
+            material: Default::default(),
+        }
+    }
+
+    fn build_bvh(vertices: &[Pnt3], indices: &[[usize; 3]]) -> Bvh {
+        let bounds = indices
+            .iter()
+            .map(|[i0, i1, i2]| Aabb::from_points(&[vertices[*i0], vertices[*i1], vertices[*i2]]))
+            .collect::<Vec<Aabb>>();
+        Bvh::build(&bounds)
+    }
+
+    fn build_area_cdf(vertices: &[Pnt3], indices: &[[usize; 3]]) -> Vec<f64> {
+        indices
+            .iter()
+            .scan(0.0, |sum, [i0, i1, i2]| {
+                *sum += triangle::triangle_area(vertices[*i0], vertices[*i1], vertices[*i2]);
+                Some(*sum)
+            })
+            .collect()
+    }
+
+    #[requires(self.indices.iter().flatten().all(|i| *i < self.vertices.len()))]
+    pub fn rebuild_bvh(&mut self) {
+        self.bvh = Self::build_bvh(&self.vertices, &self.indices);
+        self.area_cdf = Self::build_area_cdf(&self.vertices, &self.indices);
+    }
+
+    /// Total surface area of all triangles.
+    pub fn area(&self) -> f64 {
+        self.area_cdf.last().copied().unwrap_or(0.0)
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Returns the mesh with its surface made of `material`.
+    pub fn with_material(mut self, material: Material) -> Mesh {
+        self.material = material;
+        self
+    }
+
+    /// Returns the triangle at `index` as a standalone primitive.
+    #[requires(index < self.triangle_count())]
+    pub fn triangle(&self, index: usize) -> Triangle {
+        let [i0, i1, i2] = self.indices[index];
+        Triangle {
+            p0: self.vertices[i0],
+            p1: self.vertices[i1],
+            p2: self.vertices[i2],
+            normals: self.vertex_normals(index),
+            material: self.material.clone(),
+        }
+    }
+
+    fn vertex_normals(&self, index: usize) -> Option<[UnitVec3; 3]> {
+        if self.normals.is_empty() {
+            return None;
+        }
+        let [i0, i1, i2] = self.indices[index];
+        Some([self.normals[i0], self.normals[i1], self.normals[i2]])
+    }
+
+    /// Texture coordinates at the barycentric coordinates `(u, v)` of a triangle,
+    /// the barycentric coordinates themselves without texture coordinates.
+    fn interpolate_uv(&self, index: usize, u: f64, v: f64) -> (f64, f64) {
+        if self.uvs.is_empty() {
+            return (u, v);
+        }
+        let [i0, i1, i2] = self.indices[index];
+        let (uv0, uv1, uv2) = (self.uvs[i0], self.uvs[i1], self.uvs[i2]);
+        let w = 1.0 - u - v;
+        (
+            uv0.0 * w + uv1.0 * u + uv2.0 * v,
+            uv0.1 * w + uv1.1 * u + uv2.1 * v,
+        )
+    }
+}
+
+impl Hittable for Mesh {
+    fn intersect(&self, ray: &ray::Ray) -> Option<IntersectResult> {
+        let (t, (index, u, v)) = self.bvh.closest_hit(ray, |index| {
+            let [i0, i1, i2] = self.indices[index];
+            triangle::intersect_triangle(
+                ray,
+                self.vertices[i0],
+                self.vertices[i1],
+                self.vertices[i2],
+            )
+            .map(|(t, u, v)| (t, (index, u, v)))
+        })?;
+
+        let normal = match self.vertex_normals(index) {
+            Some(normals) => triangle::interpolate_normal(&normals, u, v),
+            None => {
+                let [i0, i1, i2] = self.indices[index];
+                triangle::face_normal(self.vertices[i0], self.vertices[i1], self.vertices[i2])
+            }
+        };
+        let [i0, i1, i2] = self.indices[index];
+        let (p0, p1, p2) = (self.vertices[i0], self.vertices[i1], self.vertices[i2]);
+        let uv = self.interpolate_uv(index, u, v);
+        let (dpdu, dpdv) = if self.uvs.is_empty() {
+            (p1 - p0, p2 - p0)
+        } else {
+            let (uv0, uv1, uv2) = (self.uvs[i0], self.uvs[i1], self.uvs[i2]);
+            // Solve the edges for the derivatives along the texture coordinates
+            // https://pbr-book.org/3ed-2018/Shapes/Triangle_Meshes#ShadingGeometry
+            let (du02, dv02) = (uv0.0 - uv2.0, uv0.1 - uv2.1);
+            let (du12, dv12) = (uv1.0 - uv2.0, uv1.1 - uv2.1);
+            let (dp02, dp12) = (p0 - p2, p1 - p2);
+            let det = du02 * dv12 - dv02 * du12;
+            if det.abs() < 1e-12 {
+                (p1 - p0, p2 - p0)
+            } else {
+                (
+                    (dp02 * dv12 - dp12 * dv02) / det,
+                    (dp12 * du02 - dp02 * du12) / det,
+                )
+            }
+        };
+        Some(IntersectResult {
+            t,
+            normal,
+            material: self.material.clone(),
+            uv,
+            dpdu,
+            dpdv,
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounds()
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn sample_surface(&self, u: (f64, f64), _time: f64) -> Option<SurfaceSample> {
+        if self.area() <= 0.0 {
+            return None;
+        }
+        // Pick a triangle proportional to its area, then a point on it
+        let (index, u0) = sampling::sample_cdf(&self.area_cdf, u.0);
+        let [i0, i1, i2] = self.indices[index];
+        let sample = triangle::sample_triangle(
+            self.vertices[i0],
+            self.vertices[i1],
+            self.vertices[i2],
+            (u0, u.1),
+        );
+        Some(SurfaceSample {
+            uv: self.interpolate_uv(index, sample.uv.0, sample.uv.1),
+            pdf: 1.0 / self.area(),
+            ..sample
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+    use crate::vec3::Vec3;
+
+    /// Two triangles forming the unit square in the xy plane.
+    fn quad() -> Mesh {
+        Mesh::new(
+            vec![
+                Pnt3::new(0.0, 0.0, 0.0),
+                Pnt3::new(1.0, 0.0, 0.0),
+                Pnt3::new(1.0, 1.0, 0.0),
+                Pnt3::new(0.0, 1.0, 0.0),
+            ],
+            vec![[0, 1, 2], [0, 2, 3]],
+        )
+    }
+
+    #[test]
+    fn test_intersect() {
+        let mesh = quad();
+        assert_eq!(mesh.triangle_count(), 2);
+
+        let ray = Ray::new(Pnt3::new(0.75, 0.25, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        let intersection = mesh.intersect(&ray).unwrap();
+        assert_eq!(intersection.t, 1.0);
+        assert_eq!(intersection.normal, UnitVec3::new(0.0, 0.0, 1.0));
+
+        let ray = Ray::new(Pnt3::new(0.25, 0.75, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        let intersection = mesh.intersect(&ray).unwrap();
+        assert_eq!(intersection.t, 1.0);
+
+        let ray = Ray::new(Pnt3::new(1.5, 0.5, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        assert!(mesh.intersect(&ray).is_none());
+    }
+
+    #[test]
+    fn closest_triangle_wins() {
+        let mesh = Mesh::new(
+            vec![
+                Pnt3::new(0.0, 0.0, 0.0),
+                Pnt3::new(1.0, 0.0, 0.0),
+                Pnt3::new(0.0, 1.0, 0.0),
+                Pnt3::new(0.0, 0.0, 0.5),
+                Pnt3::new(1.0, 0.0, 0.5),
+                Pnt3::new(0.0, 1.0, 0.5),
+            ],
+            vec![[0, 1, 2], [3, 4, 5]],
+        );
+        let ray = Ray::new(Pnt3::new(0.25, 0.25, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        assert_eq!(mesh.intersect(&ray).unwrap().t, 0.5);
+    }
+
+    #[test]
+    fn smooth_normals() {
+        let mesh = Mesh::with_normals(
+            vec![
+                Pnt3::new(0.0, 0.0, 0.0),
+                Pnt3::new(1.0, 0.0, 0.0),
+                Pnt3::new(0.0, 1.0, 0.0),
+            ],
+            vec![
+                UnitVec3::new(0.0, 0.0, 1.0),
+                UnitVec3::new(1.0, 0.0, 0.0),
+                UnitVec3::new(0.0, 1.0, 0.0),
+            ],
+            vec![[0, 1, 2]],
+        );
+        let ray = Ray::new(Pnt3::new(0.25, 0.25, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        let intersection = mesh.intersect(&ray).unwrap();
+        assert_eq!(intersection.normal, UnitVec3::new(0.25, 0.25, 0.5));
+        assert_eq!(mesh.triangle(0).intersect(&ray), Some(intersection));
+    }
+
+    #[test]
+    fn interpolated_texture_coordinates() {
+        let mut mesh = quad();
+        let ray = Ray::new(Pnt3::new(0.75, 0.25, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        // Barycentric coordinates without texture coordinates
+        assert_eq!(mesh.intersect(&ray).unwrap().uv, (0.5, 0.25));
+
+        mesh.uvs = vec![(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+        let hit = mesh.intersect(&ray).unwrap();
+        let uv = hit.uv;
+        assert!((uv.0 - 1.5).abs() < 1e-12 && (uv.1 - 0.5).abs() < 1e-12);
+        // Texture space is stretched by two over the quad
+        assert!((hit.dpdu - Vec3::new(0.5, 0.0, 0.0)).len() < 1e-12);
+        assert!((hit.dpdv - Vec3::new(0.0, 0.5, 0.0)).len() < 1e-12);
+    }
+
+    #[test]
+    fn surface_samples() {
+        let mesh = quad();
+        assert_eq!(mesh.area(), 1.0);
+        let mut rand = random::default(7);
+        for _ in 0..100 {
+            let u = (
+                random::Source::read_f64(&mut rand),
+                random::Source::read_f64(&mut rand),
+            );
+            let sample = mesh.sample_surface(u, 0.0).unwrap();
+            assert!((0.0..=1.0).contains(&sample.point.x));
+            assert!((0.0..=1.0).contains(&sample.point.y));
+            assert_eq!(sample.point.z, 0.0);
+            assert_eq!(sample.normal, UnitVec3::new(0.0, 0.0, 1.0));
+            assert_eq!(sample.pdf, 1.0);
+        }
+        assert!(Mesh::new(Vec::new(), Vec::new())
+            .sample_surface((0.5, 0.5), 0.0)
+            .is_none());
+
+        // Texture coordinates match those of a hit at the sampled point
+        let mut mesh = quad();
+        mesh.uvs = vec![(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+        let sample = mesh.sample_surface((0.3, 0.8), 0.0).unwrap();
+        let ray = Ray::new(
+            sample.point + Vec3::new(0.0, 0.0, 1.0),
+            UnitVec3::new(0.0, 0.0, -1.0),
+        );
+        let uv = mesh.intersect(&ray).unwrap().uv;
+        assert!((sample.uv.0 - uv.0).abs() < 1e-9 && (sample.uv.1 - uv.1).abs() < 1e-9);
+    }
+}
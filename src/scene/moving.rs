@@ -0,0 +1,261 @@
+use contracts::*;
+
+use crate::aabb::Aabb;
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::*;
+use crate::scene::{Hittable, SurfaceSample};
+use crate::vec3::{Pnt3, UnitVec3, Vec3};
+
+/// How an object moves over its [`Moving::interval`].
+/// Before and after it rests at the start and end.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Motion {
+    /// Moves along the offset.
+    Linear(Vec3),
+    /// Turns by `angle` radians around the axis through `pivot`,
+    /// counterclockwise looking against the axis. For turntables.
+    Rotation {
+        pivot: Pnt3,
+        axis: UnitVec3,
+        angle: f64,
+    },
+}
+
+/// Rotates `v` by `angle` radians around `axis`.
+/// https://en.wikipedia.org/wiki/Rodrigues%27_rotation_formula
+fn rotate(v: Vec3, axis: UnitVec3, angle: f64) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    v * cos + axis.cross(v) * sin + Vec3::from(axis) * (axis.dot(v) * (1.0 - cos))
+}
+
+/// An object moving during the exposure, blurred over the camera's shutter interval.
+/// Hits are found by moving the ray against the object's motion at the ray's time,
+/// points sampled on emitters move along with it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Moving<H> {
+    pub object: H,
+    pub motion: Motion,
+    /// Times the motion starts and ends, 0.0 to 1.0 by default.
+    /// Usually the camera's [`crate::camera::Camera::shutter`].
+    pub interval: Interval<f64>,
+}
+
+impl<H: Hittable> Moving<H> {
+    pub fn new(object: H, motion: Motion) -> Moving<H> {
+        Moving {
+            object,
+            motion,
+            interval: Interval::new(0.0, 1.0),
+        }
+    }
+
+    /// Returns the object moving from time `start` to `end`.
+    #[requires(start < end)]
+    pub fn with_interval(mut self, start: f64, end: f64) -> Moving<H> {
+        self.interval = Interval::new(start, end);
+        self
+    }
+
+    /// Fraction of the motion done at `time`, from 0.0 to 1.0.
+    fn progress(&self, time: f64) -> f64 {
+        let Interval { left, right } = self.interval;
+        ((time - left) / (right - left)).clamp(0.0, 1.0)
+    }
+}
+
+impl<H: Hittable> Hittable for Moving<H> {
+    fn intersect(&self, ray: &Ray) -> Option<IntersectResult> {
+        let s = self.progress(ray.time);
+        match self.motion {
+            Motion::Linear(offset) => {
+                let local = Ray {
+                    origin: ray.origin - offset * s,
+                    ..ray.clone()
+                };
+                self.object.intersect(&local)
+            }
+            Motion::Rotation { pivot, axis, angle } => {
+                // Rotations keep distances, so the hit is as far along both rays
+                let angle = angle * s;
+                let origin = pivot + rotate(ray.origin - pivot, axis, -angle);
+                let dir = rotate(ray.dir.into(), axis, -angle).normalize().ok()?;
+                let hit = self.object.intersect(&Ray {
+                    origin,
+                    dir,
+                    ..ray.clone()
+                })?;
+                Some(IntersectResult {
+                    normal: rotate(hit.normal.into(), axis, angle).normalize().ok()?,
+                    dpdu: rotate(hit.dpdu, axis, angle),
+                    dpdv: rotate(hit.dpdv, axis, angle),
+                    ..hit
+                })
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let aabb = self.object.bounding_box();
+        if !aabb.is_finite() {
+            return aabb;
+        }
+        match self.motion {
+            Motion::Linear(offset) => aabb.union(&Aabb::new(aabb.min + offset, aabb.max + offset)),
+            Motion::Rotation { pivot, axis, .. } => {
+                // Every corner turns on a circle around the axis, the box of the circles
+                // contains the object at every angle as the rotated box is their convex hull
+                let mut bounds = Aabb::empty();
+                for i in 0..8 {
+                    let corner = Vec3::new(
+                        if i & 1 == 0 { aabb.min.x } else { aabb.max.x },
+                        if i & 2 == 0 { aabb.min.y } else { aabb.max.y },
+                        if i & 4 == 0 { aabb.min.z } else { aabb.max.z },
+                    );
+                    let center = pivot + axis * axis.dot(corner - pivot);
+                    let radius = (corner - center).len();
+                    let extent = Vec3::new(
+                        (1.0 - axis.x * axis.x).max(0.0).sqrt(),
+                        (1.0 - axis.y * axis.y).max(0.0).sqrt(),
+                        (1.0 - axis.z * axis.z).max(0.0).sqrt(),
+                    ) * radius;
+                    bounds = bounds.union(&Aabb::new(center - extent, center + extent));
+                }
+                bounds
+            }
+        }
+    }
+
+    fn material(&self) -> &Material {
+        self.object.material()
+    }
+
+    fn sample_surface(&self, u: (f64, f64), time: f64) -> Option<SurfaceSample> {
+        let sample = self.object.sample_surface(u, time)?;
+        let s = self.progress(time);
+        match self.motion {
+            Motion::Linear(offset) => Some(SurfaceSample {
+                point: sample.point + offset * s,
+                ..sample
+            }),
+            Motion::Rotation { pivot, axis, angle } => Some(SurfaceSample {
+                point: pivot + rotate(sample.point - pivot, axis, angle * s),
+                normal: rotate(sample.normal.into(), axis, angle * s)
+                    .normalize()
+                    .ok()?,
+                ..sample
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::cube::Cube;
+    use crate::scene::quad::Quad;
+    use crate::scene::sphere::Sphere;
+    use std::f64::consts::FRAC_PI_2;
+
+    #[test]
+    fn linear_motion() {
+        let sphere = Moving::new(
+            Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 1.0),
+            Motion::Linear(Vec3::new(4.0, 0.0, 0.0)),
+        );
+        let ray = |x: f64, time: f64| {
+            Ray::new(Pnt3::new(x, 0.0, -5.0), UnitVec3::new(0.0, 0.0, 1.0)).with_time(time)
+        };
+        assert_eq!(sphere.intersect(&ray(0.0, 0.0)).unwrap().t, 4.0);
+        assert!(sphere.intersect(&ray(4.0, 0.0)).is_none());
+        assert!(sphere.intersect(&ray(0.0, 1.0)).is_none());
+        assert_eq!(sphere.intersect(&ray(4.0, 1.0)).unwrap().t, 4.0);
+        assert_eq!(sphere.intersect(&ray(2.0, 0.5)).unwrap().t, 4.0);
+        // Resting outside the motion
+        assert_eq!(sphere.intersect(&ray(4.0, 3.0)).unwrap().t, 4.0);
+        assert_eq!(sphere.intersect(&ray(0.0, -1.0)).unwrap().t, 4.0);
+
+        // Moving over another interval
+        let later = sphere.clone().with_interval(1.0, 1.5);
+        assert_eq!(later.intersect(&ray(0.0, 1.0)).unwrap().t, 4.0);
+        assert_eq!(later.intersect(&ray(2.0, 1.25)).unwrap().t, 4.0);
+        assert_eq!(later.intersect(&ray(4.0, 1.5)).unwrap().t, 4.0);
+
+        assert_eq!(
+            sphere.bounding_box(),
+            Aabb::new(Pnt3::new(-1.0, -1.0, -1.0), Pnt3::new(5.0, 1.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn rotation() {
+        let cube = Moving::new(
+            Cube::new(Pnt3::new(1.0, 0.0, -0.5), Pnt3::new(3.0, 1.0, 0.5)),
+            Motion::Rotation {
+                pivot: Pnt3::new(0.0, 0.0, 0.0),
+                axis: UnitVec3::new(0.0, 1.0, 0.0),
+                angle: FRAC_PI_2,
+            },
+        );
+        // A quarter turn around y moves the cube from +x to -z
+        let down = |x: f64, z: f64, time: f64| {
+            Ray::new(Pnt3::new(x, 5.0, z), UnitVec3::new(0.0, -1.0, 0.0)).with_time(time)
+        };
+        assert!(cube.intersect(&down(2.0, 0.0, 0.0)).is_some());
+        assert!(cube.intersect(&down(0.0, -2.0, 0.0)).is_none());
+        assert!(cube.intersect(&down(2.0, 0.0, 1.0)).is_none());
+        let hit = cube.intersect(&down(0.0, -2.0, 1.0)).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-9);
+        assert!((Vec3::from(hit.normal) - Vec3::new(0.0, 1.0, 0.0)).len() < 1e-9);
+
+        // Side faces turn with the cube
+        let side = Ray::new(Pnt3::new(0.0, 0.5, -5.0), UnitVec3::new(0.0, 0.0, 1.0)).with_time(1.0);
+        let hit = cube.intersect(&side).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-9);
+        assert!((Vec3::from(hit.normal) - Vec3::new(0.0, 0.0, -1.0)).len() < 1e-9);
+
+        // The bounds hold the cube at every angle
+        let bounds = cube.bounding_box();
+        for i in 0..=10 {
+            let time = i as f64 / 10.0;
+            let (sin, cos) = (time * FRAC_PI_2).sin_cos();
+            let far = Pnt3::new(3.0 * cos + 0.5 * sin, 1.0, -3.0 * sin + 0.5 * cos);
+            for axis in [0, 2] {
+                assert!(far[axis] >= bounds.min[axis] && far[axis] <= bounds.max[axis]);
+            }
+        }
+    }
+
+    #[test]
+    fn surface_samples_move_along() {
+        let sphere = Moving::new(
+            Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 1.0),
+            Motion::Linear(Vec3::new(4.0, 0.0, 0.0)),
+        );
+        let resting = sphere.object.sample_surface((0.3, 0.6), 0.0).unwrap();
+        let sample = sphere.sample_surface((0.3, 0.6), 0.5).unwrap();
+        assert_eq!(sample.point, resting.point + Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(sample.normal, resting.normal);
+        assert_eq!(sample.pdf, resting.pdf);
+
+        // A quarter turn around y turns a quad facing +z to face +x
+        let quad = Moving::new(
+            Quad::new(
+                Pnt3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            ),
+            Motion::Rotation {
+                pivot: Pnt3::new(0.0, 0.0, 0.0),
+                axis: UnitVec3::new(0.0, 1.0, 0.0),
+                angle: FRAC_PI_2,
+            },
+        );
+        let sample = quad.sample_surface((0.5, 0.5), 1.0).unwrap();
+        assert!((sample.point - Pnt3::new(0.0, 0.5, -1.5)).len() < 1e-9);
+        assert!((Vec3::from(sample.normal) - Vec3::new(1.0, 0.0, 0.0)).len() < 1e-9);
+        // The sample lies on the surface a ray at the same time hits
+        let ray = Ray::new(Pnt3::new(5.0, 0.5, -1.5), UnitVec3::new(-1.0, 0.0, 0.0)).with_time(1.0);
+        assert!((quad.intersect(&ray).unwrap().t - 5.0).abs() < 1e-9);
+    }
+}
@@ -1,19 +1,40 @@
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::ray::*;
-use crate::vec3::{Pnt3, UnitVec3, Vec3};
+use crate::sampling::Frame;
+use crate::scene::Hittable;
+use crate::vec3::{Pnt3, UnitVec3};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Plane {
     pub pnt: Pnt3,
     pub normal: UnitVec3,
+    pub material: Material,
 }
 
 impl Plane {
     pub fn new(pnt: Pnt3, normal: UnitVec3) -> Self {
-        Plane { pnt, normal }
+        Plane {
+            pnt,
+            normal,
+            material: Default::default(),
+        }
+    }
+
+    /// Returns the plane with its surface made of `material`.
+    pub fn with_material(mut self, material: Material) -> Plane {
+        self.material = material;
+        self
     }
+}
 
-    pub fn intersect(&self, ray: &Ray) -> Option<IntersectResult> {
+impl Hittable for Plane {
+    /// Planes are unbounded.
+    fn bounding_box(&self) -> Aabb {
+        Aabb::infinite()
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectResult> {
         let denom = self.normal.dot(ray.dir);
         if denom.abs() < 1e-6 {
             return None;
@@ -22,19 +43,22 @@ impl Plane {
         if t < 0.0 {
             return None;
         }
-        let material = Material {
-            color: Vec3::new(5.0, 150.0, 5.0),
-            albedo: 0.8,
-            roughness: 0.8,
-            refractive_index: 1.0,
-            absorption_coefficient: 0.0,
-        };
+        // Planar mapping, in world units along two tangents of the plane
+        let frame = Frame::from_normal(self.normal);
+        let offset = ray.at(t) - self.pnt;
         Some(IntersectResult {
             t,
             normal: self.normal,
-            material,
+            material: self.material.clone(),
+            uv: (frame.tangent.dot(offset), frame.bitangent.dot(offset)),
+            dpdu: frame.tangent.into(),
+            dpdv: frame.bitangent.into(),
         })
     }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
 }
 
 #[cfg(test)]
@@ -55,4 +79,17 @@ mod tests {
         let ray = Ray::new(Pnt3::new(0.5, 0.5, 1.0), UnitVec3::new(0.0, 0.0, 1.0));
         assert!(plane.intersect(&ray).is_none());
     }
+
+    #[test]
+    fn texture_coordinates() {
+        let plane = Plane::new(Pnt3::new(0.0, 0.0, 0.0), UnitVec3::new(0.0, 0.0, 1.0));
+        let uv = |x, y| {
+            let ray = Ray::new(Pnt3::new(x, y, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+            plane.intersect(&ray).unwrap().uv
+        };
+        assert_eq!(uv(0.0, 0.0), (0.0, 0.0));
+        // One unit of texture space per unit of distance
+        let (u, v) = uv(3.0, 4.0);
+        assert!((u * u + v * v - 25.0).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,139 @@
+use contracts::*;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::ray::*;
+use crate::scene::{Hittable, SurfaceSample};
+use crate::vec3::{Pnt3, UnitVec3, Vec3};
+
+/// A parallelogram spanned by the edges `u` and `v` from `corner`.
+/// Its normal follows `u × v`, quads are the usual shape of area lights.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Quad {
+    pub corner: Pnt3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub material: Material,
+}
+
+impl Quad {
+    #[requires(u.cross(v) != Vec3::null())]
+    pub fn new(corner: Pnt3, u: Vec3, v: Vec3) -> Quad {
+        Quad {
+            corner,
+            u,
+            v,
+            material: Default::default(),
+        }
+    }
+
+    /// Returns the quad with its surface made of `material`.
+    pub fn with_material(mut self, material: Material) -> Quad {
+        self.material = material;
+        self
+    }
+
+    pub fn normal(&self) -> UnitVec3 {
+        self.u.cross(self.v).normalize().unwrap()
+    }
+
+    pub fn area(&self) -> f64 {
+        self.u.cross(self.v).len()
+    }
+}
+
+impl Hittable for Quad {
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(&[
+            self.corner,
+            self.corner + self.u,
+            self.corner + self.v,
+            self.corner + self.u + self.v,
+        ])
+    }
+
+    fn intersect(&self, ray: &Ray) -> Option<IntersectResult> {
+        let n = self.u.cross(self.v);
+        let denom = n.dot(ray.dir);
+        if denom.abs() < 1e-9 {
+            return None;
+        }
+        let t = (self.corner - ray.origin).dot(n) / denom;
+        if t <= 0.0 {
+            return None;
+        }
+        // Coordinates of the hit along the edges, both in [0, 1] inside the quad
+        let p = ray.at(t) - self.corner;
+        let w = n / n.dot(n);
+        let a = w.dot(p.cross(self.v));
+        let b = w.dot(self.u.cross(p));
+        if !(0.0..=1.0).contains(&a) || !(0.0..=1.0).contains(&b) {
+            return None;
+        }
+        Some(IntersectResult {
+            t,
+            normal: self.normal(),
+            material: self.material.clone(),
+            uv: (a, b),
+            dpdu: self.u,
+            dpdv: self.v,
+        })
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn sample_surface(&self, u: (f64, f64), _time: f64) -> Option<SurfaceSample> {
+        Some(SurfaceSample {
+            point: self.corner + self.u * u.0 + self.v * u.1,
+            normal: self.normal(),
+            uv: u,
+            pdf: 1.0 / self.area(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_quad() -> Quad {
+        Quad::new(
+            Pnt3::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn test_intersect() {
+        let quad = unit_quad();
+        let ray = Ray::new(Pnt3::new(1.5, 0.5, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        let intersection = quad.intersect(&ray).unwrap();
+        assert_eq!(intersection.t, 1.0);
+        assert_eq!(intersection.normal, UnitVec3::new(0.0, 0.0, 1.0));
+        assert_eq!(intersection.uv, (0.75, 0.5));
+
+        // Outside of the edges
+        let ray = Ray::new(Pnt3::new(1.5, 1.5, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        assert!(quad.intersect(&ray).is_none());
+        let ray = Ray::new(Pnt3::new(-0.5, 0.5, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        assert!(quad.intersect(&ray).is_none());
+
+        // Pointing away
+        let ray = Ray::new(Pnt3::new(1.5, 0.5, 1.0), UnitVec3::new(0.0, 0.0, 1.0));
+        assert!(quad.intersect(&ray).is_none());
+    }
+
+    #[test]
+    fn samples_cover_the_surface() {
+        let quad = unit_quad();
+        assert_eq!(quad.area(), 2.0);
+        let sample = quad.sample_surface((0.5, 1.0), 0.0).unwrap();
+        assert_eq!(sample.point, Pnt3::new(1.0, 1.0, 0.0));
+        assert_eq!(sample.normal, UnitVec3::new(0.0, 0.0, 1.0));
+        assert_eq!(sample.uv, (0.5, 1.0));
+        assert_eq!(sample.pdf, 0.5);
+    }
+}
@@ -1,12 +1,19 @@
+use std::f64::consts::PI;
+
 use contracts::*;
 
+use crate::aabb::Aabb;
+use crate::material::Material;
 use crate::ray::{self, IntersectResult};
-use crate::vec3::{Pnt3, UnitVec3};
+use crate::sampling;
+use crate::scene::{Hittable, SurfaceSample};
+use crate::vec3::{Pnt3, UnitVec3, Vec3};
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Sphere {
     pub mid: Pnt3,
     pub r: f64,
+    pub material: Material,
 }
 
 impl Sphere {
@@ -14,10 +21,40 @@ impl Sphere {
     #[ensures(ret.mid == mid)]
     #[ensures(ret.r == r)]
     pub fn new(mid: Pnt3, r: f64) -> Sphere {
-        Sphere { mid, r }
+        Sphere {
+            mid,
+            r,
+            material: Default::default(),
+        }
+    }
+
+    /// Returns the sphere with its surface made of `material`.
+    pub fn with_material(mut self, material: Material) -> Sphere {
+        self.material = material;
+        self
+    }
+
+    /// Derivatives of the surface point along the [`spherical_uv`] coordinates
+    /// at the point in direction `dir` from the center. Null at the poles.
+    pub fn derivatives(&self, dir: UnitVec3) -> (Vec3, Vec3) {
+        let p = Vec3::from(dir) * self.r;
+        let rho = (p.x * p.x + p.z * p.z).sqrt();
+        if rho == 0.0 {
+            return (Vec3::null(), Vec3::null());
+        }
+        let dpdu = Vec3::new(p.z, 0.0, -p.x) * (2.0 * PI);
+        let dpdv = Vec3::new(-p.y * p.x / rho, rho, -p.y * p.z / rho) * PI;
+        (dpdu, dpdv)
+    }
+}
+
+impl Hittable for Sphere {
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3::new(self.r, self.r, self.r);
+        Aabb::new(self.mid - r, self.mid + r)
     }
 
-    pub fn intersect(&self, ray: &ray::Ray) -> Option<IntersectResult> {
+    fn intersect(&self, ray: &ray::Ray) -> Option<IntersectResult> {
         let a = ray.dir.dot(ray.dir);
         let b = 2.0 * ray.dir.dot(ray.origin - self.mid);
         let c = (ray.origin - self.mid).dot(ray.origin - self.mid) - self.r * self.r;
@@ -30,20 +67,48 @@ impl Sphere {
         if t_1 < 0.0 && t_2 < 0.0 {
             return None;
         }
-        let t = t_1.max(0.0).min(t_2.max(0.0));
+        // The nearer root in front of the ray, the far side for rays starting inside
+        let t = if t_2 > 0.0 { t_2 } else { t_1 };
+        let normal = (ray.at(t) - self.mid).normalize().unwrap();
+        let (dpdu, dpdv) = self.derivatives(normal);
         Some(IntersectResult {
             t,
-            normal: (ray.at(t) - self.mid).normalize().unwrap(),
-            material: Default::default(),
+            normal,
+            material: self.material.clone(),
+            uv: spherical_uv(normal),
+            dpdu,
+            dpdv,
+        })
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn sample_surface(&self, u: (f64, f64), _time: f64) -> Option<SurfaceSample> {
+        let dir = sampling::uniform_sphere(u);
+        let normal = dir.normalize().ok()?;
+        Some(SurfaceSample {
+            point: self.mid + dir * self.r,
+            normal,
+            uv: spherical_uv(normal),
+            pdf: 1.0 / (4.0 * PI * self.r * self.r),
         })
     }
 }
 
+/// Longitude and latitude of a direction from the center, both from 0.0 to 1.0.
+/// `v` runs from the bottom (-y) to the top (+y) pole.
+pub fn spherical_uv(dir: UnitVec3) -> (f64, f64) {
+    let phi = (-dir.z).atan2(dir.x) + PI;
+    let theta = (-dir.y).clamp(-1.0, 1.0).acos();
+    (phi / (2.0 * PI), theta / PI)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::ray::Ray;
-    use crate::vec3::Vec3;
 
     #[test]
     fn sphere_new() {
@@ -54,6 +119,7 @@ mod tests {
                 z: 3.0,
             },
             r: 4.0,
+            material: Default::default(),
         };
         assert_eq!(sphere.mid.x, 1.0);
         assert_eq!(sphere.mid.y, 2.0);
@@ -90,6 +156,7 @@ mod tests {
                 z: 0.0,
             },
             r: 1.0,
+            material: Default::default(),
         };
         let ray = Ray::new(
             Pnt3 {
@@ -113,7 +180,10 @@ mod tests {
                     y: 0.0,
                     z: -1.0
                 },
-                material: Default::default()
+                material: Default::default(),
+                uv: (0.75, 0.5),
+                dpdu: Vec3::new(-2.0 * PI, 0.0, 0.0),
+                dpdv: Vec3::new(0.0, PI, 0.0),
             })
         );
 
@@ -155,6 +225,7 @@ mod tests {
                 z: -50.0,
             },
             r: 1.0,
+            material: Default::default(),
         };
         let ray = Ray::new(
             Vec3 {
@@ -178,19 +249,71 @@ mod tests {
                 z: -500.0,
             },
             r: 100.0,
+            material: Default::default(),
         };
         let intersection = sphere.intersect(&ray);
+        let normal = UnitVec3 {
+            x: 0.009999999999999995,
+            y: -0.4799999999999998,
+            z: 0.8772114910328067,
+        };
+        let (dpdu, dpdv) = sphere.derivatives(normal);
         assert_eq!(
             intersection,
             Some(IntersectResult {
                 t: 432.2788508967193,
-                normal: UnitVec3 {
-                    x: 0.009999999999999995,
-                    y: -0.4799999999999998,
-                    z: 0.8772114910328067
-                },
-                material: Default::default()
+                normal,
+                material: Default::default(),
+                uv: spherical_uv(normal),
+                dpdu,
+                dpdv,
             })
         );
     }
+
+    #[test]
+    fn rays_from_inside() {
+        let sphere = Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 2.0);
+        let ray = Ray::new(Pnt3::new(0.0, 0.0, 1.0), UnitVec3::new(0.0, 0.0, 1.0));
+        let intersection = sphere.intersect(&ray).unwrap();
+        assert_eq!(intersection.t, 1.0);
+        assert_eq!(intersection.normal, UnitVec3::new(0.0, 0.0, 1.0));
+
+        let ray = Ray::new(Pnt3::new(0.0, 0.0, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        assert_eq!(sphere.intersect(&ray).unwrap().t, 3.0);
+    }
+
+    #[test]
+    fn surface_samples() {
+        let sphere = Sphere::new(Pnt3::new(1.0, 2.0, 3.0), 2.0);
+        let sample = sphere.sample_surface((0.3, 0.6), 0.0).unwrap();
+        assert!(((sample.point - sphere.mid).len() - 2.0).abs() < 1e-12);
+        assert!((sphere.mid + sample.normal * 2.0 - sample.point).len() < 1e-12);
+        assert_eq!(sample.pdf, 1.0 / (16.0 * PI));
+    }
+
+    #[test]
+    fn texture_coordinates() {
+        assert_eq!(spherical_uv(UnitVec3::new(0.0, -1.0, 0.0)).1, 0.0);
+        assert_eq!(spherical_uv(UnitVec3::new(0.0, 1.0, 0.0)).1, 1.0);
+        assert_eq!(spherical_uv(UnitVec3::new(1.0, 0.0, 0.0)), (0.5, 0.5));
+        assert_eq!(spherical_uv(UnitVec3::new(0.0, 0.0, -1.0)), (0.75, 0.5));
+        assert_eq!(spherical_uv(UnitVec3::new(0.0, 0.0, 1.0)), (0.25, 0.5));
+    }
+
+    #[test]
+    fn derivatives_follow_the_texture_coordinates() {
+        let sphere = Sphere::new(Pnt3::new(0.0, 0.0, 0.0), 2.0);
+        let dir = UnitVec3::new(0.3, 0.4, -0.5);
+        let (dpdu, dpdv) = sphere.derivatives(dir);
+        let (u, v) = spherical_uv(dir);
+        // Step along the derivatives and compare the texture coordinates there
+        let h = 1e-6;
+        let uv_at = |d: Vec3| spherical_uv((Vec3::from(dir) * 2.0 + d * h).normalize().unwrap());
+        let (u1, v1) = uv_at(dpdu);
+        assert!(((u1 - u) / h - 1.0).abs() < 1e-4 && ((v1 - v) / h).abs() < 1e-4);
+        let (u2, v2) = uv_at(dpdv);
+        assert!(((u2 - u) / h).abs() < 1e-4 && ((v2 - v) / h - 1.0).abs() < 1e-4);
+        assert!(dpdu.dot(dir).abs() < 1e-12 && dpdv.dot(dir).abs() < 1e-12);
+    }
 }
@@ -0,0 +1,241 @@
+use contracts::*;
+
+use crate::aabb::Aabb;
+use crate::material::Material;
+use crate::ray::{self, IntersectResult};
+use crate::sampling;
+use crate::scene::{Hittable, SurfaceSample};
+use crate::vec3::{Pnt3, UnitVec3};
+
+/// Rays closer to parallel with the triangle plane than this are treated as misses.
+const EPSILON: f64 = 1e-9;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Triangle {
+    pub p0: Pnt3,
+    pub p1: Pnt3,
+    pub p2: Pnt3,
+    /// Optional per-vertex normals, interpolated with the barycentric coordinates of the hit.
+    /// Without them the geometric face normal is used.
+    pub normals: Option<[UnitVec3; 3]>,
+    pub material: Material,
+}
+
+impl Triangle {
+    #[requires(p0 != p1 && p1 != p2 && p0 != p2)]
+    pub fn new(p0: Pnt3, p1: Pnt3, p2: Pnt3) -> Triangle {
+        Triangle {
+            p0,
+            p1,
+            p2,
+            normals: None,
+            material: Default::default(),
+        }
+    }
+
+    #[requires(p0 != p1 && p1 != p2 && p0 != p2)]
+    pub fn with_normals(p0: Pnt3, p1: Pnt3, p2: Pnt3, normals: [UnitVec3; 3]) -> Triangle {
+        Triangle {
+            p0,
+            p1,
+            p2,
+            normals: Some(normals),
+            material: Default::default(),
+        }
+    }
+
+    /// Returns the triangle with its surface made of `material`.
+    pub fn with_material(mut self, material: Material) -> Triangle {
+        self.material = material;
+        self
+    }
+
+    pub fn area(&self) -> f64 {
+        triangle_area(self.p0, self.p1, self.p2)
+    }
+
+    /// The geometric normal, following the counter-clockwise winding of `p0`, `p1`, `p2`.
+    pub fn normal(&self) -> UnitVec3 {
+        face_normal(self.p0, self.p1, self.p2)
+    }
+}
+
+impl Hittable for Triangle {
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(&[self.p0, self.p1, self.p2])
+    }
+
+    fn intersect(&self, ray: &ray::Ray) -> Option<IntersectResult> {
+        let (t, u, v) = intersect_triangle(ray, self.p0, self.p1, self.p2)?;
+        let normal = match &self.normals {
+            Some(normals) => interpolate_normal(normals, u, v),
+            None => self.normal(),
+        };
+        Some(IntersectResult {
+            t,
+            normal,
+            material: self.material.clone(),
+            uv: (u, v),
+            dpdu: self.p1 - self.p0,
+            dpdv: self.p2 - self.p0,
+        })
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn sample_surface(&self, u: (f64, f64), _time: f64) -> Option<SurfaceSample> {
+        Some(sample_triangle(self.p0, self.p1, self.p2, u))
+    }
+}
+
+/// Möller–Trumbore ray/triangle intersection.
+/// https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
+///
+/// Returns the distance along the ray and the barycentric coordinates `(u, v)` of the hit,
+/// where `u` is the weight of `p1` and `v` the weight of `p2`.
+pub fn intersect_triangle(ray: &ray::Ray, p0: Pnt3, p1: Pnt3, p2: Pnt3) -> Option<(f64, f64, f64)> {
+    let edge1 = p1 - p0;
+    let edge2 = p2 - p0;
+    let h = ray.dir.cross(edge2);
+    let det = edge1.dot(h);
+    if det.abs() < EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+    let s = ray.origin - p0;
+    let u = inv_det * s.dot(h);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = inv_det * ray.dir.dot(q);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = inv_det * edge2.dot(q);
+    if t < EPSILON {
+        return None;
+    }
+    Some((t, u, v))
+}
+
+/// The normal of the plane through `p0`, `p1`, `p2` with counter-clockwise winding.
+pub fn face_normal(p0: Pnt3, p1: Pnt3, p2: Pnt3) -> UnitVec3 {
+    (p1 - p0).cross(p2 - p0).normalize().unwrap()
+}
+
+pub fn triangle_area(p0: Pnt3, p1: Pnt3, p2: Pnt3) -> f64 {
+    (p1 - p0).cross(p2 - p0).len() / 2.0
+}
+
+/// Samples a point uniformly distributed over the triangle, with its face normal
+/// and its barycentric coordinates as texture coordinates.
+pub fn sample_triangle(p0: Pnt3, p1: Pnt3, p2: Pnt3, u: (f64, f64)) -> SurfaceSample {
+    let (b1, b2) = sampling::uniform_triangle(u);
+    SurfaceSample {
+        point: p0 * (1.0 - b1 - b2) + p1 * b1 + p2 * b2,
+        normal: face_normal(p0, p1, p2),
+        uv: (b1, b2),
+        pdf: 1.0 / triangle_area(p0, p1, p2),
+    }
+}
+
+/// Blends the three vertex normals with the barycentric coordinates `(u, v)`
+/// as returned by [`intersect_triangle`].
+pub fn interpolate_normal(normals: &[UnitVec3; 3], u: f64, v: f64) -> UnitVec3 {
+    let w = 1.0 - u - v;
+    let normal = normals[0] * w + normals[1] * u + normals[2] * v;
+    normal.normalize().unwrap_or(normals[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ray::Ray;
+
+    fn unit_triangle() -> Triangle {
+        Triangle::new(
+            Pnt3::new(0.0, 0.0, 0.0),
+            Pnt3::new(1.0, 0.0, 0.0),
+            Pnt3::new(0.0, 1.0, 0.0),
+        )
+    }
+
+    #[test]
+    fn test_intersect() {
+        let triangle = unit_triangle();
+        let ray = Ray::new(Pnt3::new(0.25, 0.25, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        let intersection = triangle.intersect(&ray).unwrap();
+        assert_eq!(intersection.t, 1.0);
+        assert_eq!(intersection.normal, UnitVec3::new(0.0, 0.0, 1.0));
+
+        // Hits from behind report the same face normal
+        let ray = Ray::new(Pnt3::new(0.25, 0.25, -2.0), UnitVec3::new(0.0, 0.0, 1.0));
+        let intersection = triangle.intersect(&ray).unwrap();
+        assert_eq!(intersection.t, 2.0);
+        assert_eq!(intersection.normal, UnitVec3::new(0.0, 0.0, 1.0));
+
+        // Outside of the edges
+        let ray = Ray::new(Pnt3::new(0.75, 0.75, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        assert!(triangle.intersect(&ray).is_none());
+
+        // Pointing away
+        let ray = Ray::new(Pnt3::new(0.25, 0.25, 1.0), UnitVec3::new(0.0, 0.0, 1.0));
+        assert!(triangle.intersect(&ray).is_none());
+
+        // Parallel to the plane
+        let ray = Ray::new(Pnt3::new(-1.0, 0.25, 0.0), UnitVec3::new(1.0, 0.0, 0.0));
+        assert!(triangle.intersect(&ray).is_none());
+    }
+
+    #[test]
+    fn barycentric_coordinates() {
+        let ray = Ray::new(Pnt3::new(0.25, 0.5, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        let (t, u, v) = intersect_triangle(
+            &ray,
+            Pnt3::new(0.0, 0.0, 0.0),
+            Pnt3::new(1.0, 0.0, 0.0),
+            Pnt3::new(0.0, 1.0, 0.0),
+        )
+        .unwrap();
+        assert_eq!(t, 1.0);
+        assert_eq!(u, 0.25);
+        assert_eq!(v, 0.5);
+        assert_eq!(unit_triangle().intersect(&ray).unwrap().uv, (0.25, 0.5));
+    }
+
+    #[test]
+    fn interpolated_normals() {
+        let normals = [
+            UnitVec3::new(0.0, 0.0, 1.0),
+            UnitVec3::new(1.0, 0.0, 0.0),
+            UnitVec3::new(0.0, 1.0, 0.0),
+        ];
+        assert_eq!(interpolate_normal(&normals, 0.0, 0.0), normals[0]);
+        assert_eq!(interpolate_normal(&normals, 1.0, 0.0), normals[1]);
+        assert_eq!(interpolate_normal(&normals, 0.0, 1.0), normals[2]);
+
+        let triangle = Triangle::with_normals(
+            Pnt3::new(0.0, 0.0, 0.0),
+            Pnt3::new(1.0, 0.0, 0.0),
+            Pnt3::new(0.0, 1.0, 0.0),
+            normals,
+        );
+        let ray = Ray::new(Pnt3::new(0.5, 0.0, 1.0), UnitVec3::new(0.0, 0.0, -1.0));
+        let intersection = triangle.intersect(&ray).unwrap();
+        assert_eq!(intersection.normal, UnitVec3::new(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn surface_samples() {
+        let triangle = unit_triangle();
+        assert_eq!(triangle.area(), 0.5);
+        let sample = triangle.sample_surface((0.25, 0.5), 0.0).unwrap();
+        assert_eq!(sample.point, Pnt3::new(0.25, 0.25, 0.0));
+        assert_eq!(sample.normal, UnitVec3::new(0.0, 0.0, 1.0));
+        assert_eq!(sample.uv, (0.25, 0.25));
+        assert_eq!(sample.pdf, 2.0);
+    }
+}
@@ -0,0 +1,120 @@
+//! Textures, varying material parameters over a surface.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::vec3::{Pnt3, Vec3};
+
+pub mod image;
+pub mod noise;
+
+pub use self::image::{ColorSpace, Filter, ImageTexture, WrapMode};
+pub use self::noise::{NoiseTexture, Pattern, Perlin};
+
+/// A value varying over surfaces, looked up by texture coordinates or position.
+/// Values are in the units of the material parameter they replace,
+/// e.g. 0.0 to 255.0 for colors.
+pub trait Texture: Send + Sync + Debug {
+    /// The value at the texture coordinates `uv` of the surface point `p`,
+    /// averaged over a region `footprint` wide in texture coordinates where the texture
+    /// can filter. A footprint of 0.0 looks up the value at the point.
+    fn value(&self, uv: (f64, f64), p: Pnt3, footprint: f64) -> Vec3;
+}
+
+/// A shared reference to a texture, compared by identity.
+#[derive(Debug, Clone)]
+pub struct TextureRef(Arc<dyn Texture>);
+
+impl TextureRef {
+    pub fn new(texture: impl Texture + 'static) -> TextureRef {
+        TextureRef(Arc::new(texture))
+    }
+
+    pub fn value(&self, uv: (f64, f64), p: Pnt3, footprint: f64) -> Vec3 {
+        self.0.value(uv, p, footprint)
+    }
+}
+
+impl PartialEq for TextureRef {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// The same value everywhere.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ConstantTexture {
+    pub value: Vec3,
+}
+
+impl ConstantTexture {
+    pub fn new(value: Vec3) -> ConstantTexture {
+        ConstantTexture { value }
+    }
+}
+
+impl Texture for ConstantTexture {
+    fn value(&self, _uv: (f64, f64), _p: Pnt3, _footprint: f64) -> Vec3 {
+        self.value
+    }
+}
+
+/// Alternating squares of two values in texture space.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Checkerboard {
+    pub even: Vec3,
+    pub odd: Vec3,
+    /// Squares per unit of texture coordinates.
+    pub scale: f64,
+}
+
+impl Checkerboard {
+    pub fn new(even: Vec3, odd: Vec3, scale: f64) -> Checkerboard {
+        Checkerboard { even, odd, scale }
+    }
+}
+
+impl Texture for Checkerboard {
+    fn value(&self, uv: (f64, f64), _p: Pnt3, _footprint: f64) -> Vec3 {
+        let parity = (uv.0 * self.scale).floor() + (uv.1 * self.scale).floor();
+        if parity.rem_euclid(2.0) == 0.0 {
+            self.even
+        } else {
+            self.odd
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkerboard() {
+        let checker = Checkerboard::new(Vec3::new(1.0, 1.0, 1.0), Vec3::null(), 2.0);
+        let p = Pnt3::null();
+        assert_eq!(checker.value((0.1, 0.1), p, 0.0), Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(checker.value((0.6, 0.1), p, 0.0), Vec3::null());
+        assert_eq!(checker.value((0.6, 0.6), p, 0.0), Vec3::new(1.0, 1.0, 1.0));
+        // Continues into negative coordinates
+        assert_eq!(checker.value((-0.1, 0.1), p, 0.0), Vec3::null());
+        assert_eq!(
+            checker.value((-0.1, -0.1), p, 0.0),
+            Vec3::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn references_compare_by_identity() {
+        let texture = TextureRef::new(ConstantTexture::new(Vec3::new(1.0, 2.0, 3.0)));
+        assert_eq!(texture, texture.clone());
+        assert_ne!(
+            texture,
+            TextureRef::new(ConstantTexture::new(Vec3::new(1.0, 2.0, 3.0)))
+        );
+        assert_eq!(
+            texture.value((0.5, 0.5), Pnt3::null(), 0.0),
+            Vec3::new(1.0, 2.0, 3.0)
+        );
+    }
+}
@@ -0,0 +1,560 @@
+use std::path::Path;
+
+use anyhow::{anyhow, bail};
+use contracts::*;
+
+use crate::color::Color;
+use crate::texture::Texture;
+use crate::vec3::{Pnt3, Vec3};
+
+/// How lookups outside of the unit square of texture coordinates are resolved.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum WrapMode {
+    /// Tiles the image.
+    #[default]
+    Repeat,
+    /// Extends the edge pixels.
+    Clamp,
+    /// Tiles the image, flipping every other tile.
+    Mirror,
+}
+
+impl WrapMode {
+    /// Maps a pixel index, possibly outside of the image, to one inside of `0..size`.
+    fn wrap(self, i: i64, size: usize) -> usize {
+        let size = size as i64;
+        let i = match self {
+            WrapMode::Repeat => i.rem_euclid(size),
+            WrapMode::Clamp => i.clamp(0, size - 1),
+            WrapMode::Mirror => {
+                let i = i.rem_euclid(2 * size);
+                if i < size {
+                    i
+                } else {
+                    2 * size - 1 - i
+                }
+            }
+        };
+        i as usize
+    }
+}
+
+/// How the samples of a loaded image are encoded.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum ColorSpace {
+    /// The sRGB transfer function, how 8-bit colors are stored.
+    /// Decoded to linear values when loading, so filtering averages light.
+    /// https://en.wikipedia.org/wiki/SRGB#Transfer_function_(%22gamma%22)
+    #[default]
+    Srgb,
+    /// Stored as is, for data like roughness, normal and bump maps.
+    Linear,
+}
+
+impl ColorSpace {
+    /// Decodes one sample between 0.0 and 255.0 to a linear value in the same range.
+    fn decode(self, x: f64) -> f64 {
+        match self {
+            ColorSpace::Srgb => {
+                let x = x / 255.0;
+                let linear = if x <= 0.04045 {
+                    x / 12.92
+                } else {
+                    ((x + 0.055) / 1.055).powf(2.4)
+                };
+                linear * 255.0
+            }
+            ColorSpace::Linear => x,
+        }
+    }
+}
+
+/// How pixels are interpolated between their centers.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Filter {
+    Nearest,
+    #[default]
+    Bilinear,
+}
+
+/// One resolution of the mipmap pyramid, rows from top to bottom.
+#[derive(Debug, PartialEq, Clone)]
+struct Level {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl Level {
+    /// Halves the resolution by averaging 2x2 blocks, odd edges are repeated.
+    fn downsample(&self) -> Level {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        // Only a hint, a level never holds more pixels than the one it was halved from
+        let mut pixels = Vec::with_capacity(width.checked_mul(height).unwrap_or_default());
+        for y in 0..height {
+            for x in 0..width {
+                let x0 = (2 * x).min(self.width - 1);
+                let x1 = (2 * x + 1).min(self.width - 1);
+                let y0 = (2 * y).min(self.height - 1);
+                let y1 = (2 * y + 1).min(self.height - 1);
+                let sum = self.pixels[y0 * self.width + x0]
+                    + self.pixels[y0 * self.width + x1]
+                    + self.pixels[y1 * self.width + x0]
+                    + self.pixels[y1 * self.width + x1];
+                pixels.push(sum / 4.0);
+            }
+        }
+        Level {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// A bitmap stretched over the unit square of texture coordinates.
+/// `v` runs from the bottom to the top row, values are in the units of the pixels,
+/// 0.0 to 255.0 for loaded images.
+/// https://pbr-book.org/3ed-2018/Texture/Image_Texture
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImageTexture {
+    /// The mipmap pyramid, from full resolution down to a single pixel.
+    levels: Vec<Level>,
+    pub wrap: WrapMode,
+    pub filter: Filter,
+}
+
+impl ImageTexture {
+    #[requires(width > 0 && height > 0)]
+    #[requires(pixels.len() == width * height)]
+    pub fn new(width: usize, height: usize, pixels: Vec<Vec3>) -> ImageTexture {
+        let mut levels = vec![Level {
+            width,
+            height,
+            pixels,
+        }];
+        while let Some(last) = levels.last().filter(|l| l.width > 1 || l.height > 1) {
+            let next = last.downsample();
+            levels.push(next);
+        }
+        ImageTexture {
+            levels,
+            wrap: Default::default(),
+            filter: Default::default(),
+        }
+    }
+
+    #[requires(width > 0 && height > 0)]
+    #[requires(pixels.len() == width * height)]
+    pub fn from_colors(width: usize, height: usize, pixels: &[Color]) -> ImageTexture {
+        let pixels = pixels
+            .iter()
+            .map(|color| Vec3::new(color.r, color.g, color.b))
+            .collect();
+        ImageTexture::new(width, height, pixels)
+    }
+
+    /// Loads a PPM (P3 or P6) or PNG file, told apart by their magic bytes,
+    /// with samples encoded in `color_space`.
+    pub fn load(path: impl AsRef<Path>, color_space: ColorSpace) -> anyhow::Result<ImageTexture> {
+        let data = std::fs::read(path)?;
+        if data.starts_with(b"\x89PNG") {
+            ImageTexture::from_png(&data, color_space)
+        } else if data.starts_with(b"P3") || data.starts_with(b"P6") {
+            ImageTexture::from_ppm(&data, color_space)
+        } else {
+            bail!("Unknown image format")
+        }
+    }
+
+    /// Parses a plain (P3) or binary (P6) PPM, scaling samples to 0.0 to 255.0
+    /// and decoding them from `color_space`.
+    /// https://netpbm.sourceforge.net/doc/ppm.html
+    pub fn from_ppm(data: &[u8], color_space: ColorSpace) -> anyhow::Result<ImageTexture> {
+        let mut pos = 0;
+        let magic = next_token(data, &mut pos)?;
+        let binary = match magic {
+            b"P3" => false,
+            b"P6" => true,
+            _ => bail!("Not a PPM file"),
+        };
+        let width = parse_number(next_token(data, &mut pos)?)?;
+        let height = parse_number(next_token(data, &mut pos)?)?;
+        let max = parse_number(next_token(data, &mut pos)?)?;
+        if width == 0 || height == 0 || max == 0 || max > 65535 {
+            bail!("Invalid PPM header");
+        }
+        let scale = 255.0 / max as f64;
+        let count = match width.checked_mul(height).and_then(|n| n.checked_mul(3)) {
+            Some(count) => count,
+            None => bail!("PPM too large"),
+        };
+
+        let samples: Vec<f64> = if binary {
+            // A single whitespace separates the header from the raster
+            let raster = data.get(pos + 1..).unwrap_or_default();
+            let size = if max > 255 { 2 } else { 1 };
+            if raster.len() / size < count {
+                bail!("PPM raster is truncated");
+            }
+            raster
+                .chunks_exact(size)
+                .take(count)
+                .map(|b| b.iter().fold(0, |acc, &b| acc << 8 | b as usize) as f64 * scale)
+                .collect()
+        } else {
+            (0..count)
+                .map(|_| Ok(parse_number(next_token(data, &mut pos)?)? as f64 * scale))
+                .collect::<anyhow::Result<_>>()?
+        };
+        let pixels = samples
+            .chunks_exact(3)
+            .map(|c| {
+                let [r, g, b] = [c[0], c[1], c[2]].map(|x| color_space.decode(x));
+                Vec3::new(r, g, b)
+            })
+            .collect();
+        Ok(ImageTexture::new(width, height, pixels))
+    }
+
+    /// Decodes a PNG of any color type and bit depth, dropping the alpha channel
+    /// and decoding the samples from `color_space`.
+    pub fn from_png(data: &[u8], color_space: ColorSpace) -> anyhow::Result<ImageTexture> {
+        let mut decoder = png::Decoder::new(std::io::Cursor::new(data));
+        // Palettes, grayscale below 8 bits and transparency to plain channels
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info()?;
+        let size = reader
+            .output_buffer_size()
+            .ok_or_else(|| anyhow!("PNG is too large"))?;
+        let mut buf = vec![0; size];
+        let info = reader.next_frame(&mut buf)?;
+        let buf = &buf[..info.buffer_size()];
+
+        let (bytes, max) = match info.bit_depth {
+            png::BitDepth::Sixteen => (2, 65535.0),
+            _ => (1, 255.0),
+        };
+        let samples: Vec<f64> = buf
+            .chunks_exact(bytes)
+            .map(|b| b.iter().fold(0, |acc, &b| acc << 8 | b as u32) as f64 * 255.0 / max)
+            .map(|x| color_space.decode(x))
+            .collect();
+        let pixels = match info.color_type {
+            png::ColorType::Grayscale => samples.iter().map(|&g| Vec3::new(g, g, g)).collect(),
+            png::ColorType::GrayscaleAlpha => samples
+                .chunks_exact(2)
+                .map(|c| Vec3::new(c[0], c[0], c[0]))
+                .collect(),
+            png::ColorType::Rgb => samples
+                .chunks_exact(3)
+                .map(|c| Vec3::new(c[0], c[1], c[2]))
+                .collect(),
+            png::ColorType::Rgba => samples
+                .chunks_exact(4)
+                .map(|c| Vec3::new(c[0], c[1], c[2]))
+                .collect(),
+            png::ColorType::Indexed => bail!("PNG palette was not expanded"),
+        };
+        Ok(ImageTexture::new(
+            info.width as usize,
+            info.height as usize,
+            pixels,
+        ))
+    }
+
+    /// Returns the texture with lookups outside of the image resolved by `wrap`.
+    pub fn with_wrap(mut self, wrap: WrapMode) -> ImageTexture {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Returns the texture with pixels interpolated by `filter`.
+    pub fn with_filter(mut self, filter: Filter) -> ImageTexture {
+        self.filter = filter;
+        self
+    }
+
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+
+    /// Number of resolutions in the mipmap pyramid.
+    pub fn mip_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    #[requires(x < self.width() && y < self.height())]
+    pub fn pixel(&self, x: usize, y: usize) -> Vec3 {
+        self.levels[0].pixels[y * self.width() + x]
+    }
+
+    /// Looks the texture up in the mipmap `level`, 0 is the full resolution.
+    #[requires(level < self.mip_levels())]
+    pub fn lookup(&self, uv: (f64, f64), level: usize) -> Vec3 {
+        let level = &self.levels[level];
+        let texel = |x: i64, y: i64| {
+            let x = self.wrap.wrap(x, level.width);
+            let y = self.wrap.wrap(y, level.height);
+            level.pixels[y * level.width + x]
+        };
+        // Continuous pixel coordinates, with pixel centers at half integers
+        let x = uv.0 * level.width as f64;
+        let y = (1.0 - uv.1) * level.height as f64;
+        match self.filter {
+            Filter::Nearest => texel(x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                // Huge coordinates saturate the indices, their neighbours must not overflow
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let (x1, y1) = (x0.saturating_add(1), y0.saturating_add(1));
+                let top = texel(x0, y0) * (1.0 - fx) + texel(x1, y0) * fx;
+                let bottom = texel(x0, y1) * (1.0 - fx) + texel(x1, y1) * fx;
+                top * (1.0 - fy) + bottom * fy
+            }
+        }
+    }
+
+    /// Looks the texture up averaged over a region `footprint` wide in texture coordinates,
+    /// blending between the two closest mipmap levels to avoid aliasing when minified.
+    #[requires(footprint >= 0.0)]
+    pub fn filtered(&self, uv: (f64, f64), footprint: f64) -> Vec3 {
+        let pixels = footprint * self.width().max(self.height()) as f64;
+        let level = pixels.max(1.0).log2().min((self.mip_levels() - 1) as f64);
+        let lower = level.floor() as usize;
+        let t = level - lower as f64;
+        if t == 0.0 {
+            return self.lookup(uv, lower);
+        }
+        self.lookup(uv, lower) * (1.0 - t) + self.lookup(uv, lower + 1) * t
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, uv: (f64, f64), _p: Pnt3, footprint: f64) -> Vec3 {
+        self.filtered(uv, footprint)
+    }
+}
+
+/// Skips whitespace and `#` comments, returning the next whitespace separated token.
+fn next_token<'a>(data: &'a [u8], pos: &mut usize) -> anyhow::Result<&'a [u8]> {
+    while *pos < data.len() {
+        if data[*pos] == b'#' {
+            while *pos < data.len() && data[*pos] != b'\n' {
+                *pos += 1;
+            }
+        } else if data[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        } else {
+            break;
+        }
+    }
+    let start = *pos;
+    while *pos < data.len() && !data[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+    if start == *pos {
+        bail!("Unexpected end of PPM data");
+    }
+    Ok(&data[start..*pos])
+}
+
+fn parse_number(token: &[u8]) -> anyhow::Result<usize> {
+    Ok(std::str::from_utf8(token)?.parse()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checker() -> ImageTexture {
+        ImageTexture::from_colors(
+            2,
+            2,
+            &[
+                Color::black(),
+                Color::white(),
+                Color::white(),
+                Color::black(),
+            ],
+        )
+    }
+
+    #[test]
+    fn lookup() {
+        let texture = checker();
+        let p = Pnt3::null();
+        // Top left
+        assert_eq!(texture.value((0.25, 0.75), p, 0.0), Vec3::null());
+        // Bottom left
+        assert_eq!(
+            texture.value((0.25, 0.25), p, 0.0),
+            Vec3::new(255.0, 255.0, 255.0)
+        );
+        // Repeats
+        assert_eq!(
+            texture.value((1.25, -0.75), p, 0.0),
+            Vec3::new(255.0, 255.0, 255.0)
+        );
+        assert_eq!(
+            texture.value((1.0, 1.0), p, 0.0),
+            texture.value((0.0, 0.0), p, 0.0)
+        );
+    }
+
+    #[test]
+    fn bilinear_filtering() {
+        let texture = checker();
+        let p = Pnt3::null();
+        // Between all four pixels
+        assert_eq!(
+            texture.value((0.5, 0.5), p, 0.0),
+            Vec3::new(127.5, 127.5, 127.5)
+        );
+        // Halfway between the top two pixels
+        assert_eq!(
+            texture.value((0.5, 0.75), p, 0.0),
+            Vec3::new(127.5, 127.5, 127.5)
+        );
+        let nearest = checker().with_filter(Filter::Nearest);
+        assert_eq!(nearest.value((0.4, 0.6), p, 0.0), Vec3::null());
+        assert_ne!(texture.value((0.4, 0.6), p, 0.0), Vec3::null());
+    }
+
+    #[test]
+    fn wrap_modes() {
+        let ramp = |wrap| {
+            let pixels = (0..4).map(|x| Vec3::new(x as f64, 0.0, 0.0)).collect();
+            ImageTexture::new(4, 1, pixels)
+                .with_wrap(wrap)
+                .with_filter(Filter::Nearest)
+        };
+        let x = |texture: &ImageTexture, u| texture.value((u, 0.5), Pnt3::null(), 0.0).x;
+        assert_eq!(x(&ramp(WrapMode::Repeat), 1.125), 0.0);
+        assert_eq!(x(&ramp(WrapMode::Repeat), -0.125), 3.0);
+        assert_eq!(x(&ramp(WrapMode::Clamp), 1.125), 3.0);
+        assert_eq!(x(&ramp(WrapMode::Clamp), -0.125), 0.0);
+        assert_eq!(x(&ramp(WrapMode::Mirror), 1.125), 3.0);
+        assert_eq!(x(&ramp(WrapMode::Mirror), 1.875), 0.0);
+        assert_eq!(x(&ramp(WrapMode::Mirror), -0.125), 0.0);
+    }
+
+    #[test]
+    fn huge_coordinates() {
+        for wrap in [WrapMode::Repeat, WrapMode::Clamp, WrapMode::Mirror] {
+            let texture = checker().with_wrap(wrap);
+            for uv in [(1e300, 1e300), (-1e300, 0.5), (0.5, -1e300)] {
+                let value = texture.lookup(uv, 0);
+                assert!(value.x.is_finite() && value.x >= 0.0 && value.x <= 255.0);
+            }
+        }
+    }
+
+    #[test]
+    fn mipmaps() {
+        let texture = ImageTexture::new(4, 2, vec![Vec3::new(8.0, 0.0, 0.0); 8]);
+        assert_eq!(texture.mip_levels(), 3);
+        let texture = checker();
+        assert_eq!(texture.mip_levels(), 2);
+        let average = Vec3::new(127.5, 127.5, 127.5);
+        assert_eq!(texture.lookup((0.25, 0.75), 1), average);
+        // A footprint covering the whole image only sees the average
+        assert_eq!(texture.filtered((0.25, 0.75), 1.0), average);
+        // A footprint of a pixel sees the full resolution
+        assert_eq!(texture.filtered((0.25, 0.75), 0.5), Vec3::null());
+        let between = texture.filtered((0.25, 0.75), 0.75).x;
+        assert!(between > 0.0 && between < 127.5);
+        // Lookups through the texture interface are filtered by their footprint
+        assert_eq!(texture.value((0.25, 0.75), Pnt3::null(), 1.0), average);
+        assert_eq!(texture.value((0.25, 0.75), Pnt3::null(), 0.0), Vec3::null());
+    }
+
+    #[test]
+    fn plain_ppm() {
+        let data = b"P3\n# A comment\n2 1\n15\n15 0 0  0 15 15\n";
+        let texture = ImageTexture::from_ppm(data, ColorSpace::Linear).unwrap();
+        assert_eq!((texture.width(), texture.height()), (2, 1));
+        assert_eq!(texture.pixel(0, 0), Vec3::new(255.0, 0.0, 0.0));
+        assert_eq!(texture.pixel(1, 0), Vec3::new(0.0, 255.0, 255.0));
+        assert!(ImageTexture::from_ppm(b"P3\n2 1\n255\n1 2 3", ColorSpace::Linear).is_err());
+    }
+
+    #[test]
+    fn huge_ppm_headers() {
+        for header in [
+            "P6 4294967296 4294967296 255\n",
+            "P3 18446744073709551615 2 255\n",
+            "P6 6148914691236517206 1 65535\n",
+        ] {
+            let error = ImageTexture::from_ppm(header.as_bytes(), ColorSpace::Linear).unwrap_err();
+            assert_eq!(error.to_string(), "PPM too large");
+        }
+        // Fits into memory in theory, but not into the data
+        assert!(ImageTexture::from_ppm(b"P6 65536 65536 65535\n", ColorSpace::Linear).is_err());
+    }
+
+    #[test]
+    fn binary_ppm() {
+        let mut data = b"P6 1 2 255\n".to_vec();
+        data.extend_from_slice(&[255, 128, 0, 10, 20, 30]);
+        let texture = ImageTexture::from_ppm(&data, ColorSpace::Linear).unwrap();
+        assert_eq!(texture.pixel(0, 0), Vec3::new(255.0, 128.0, 0.0));
+        assert_eq!(texture.pixel(0, 1), Vec3::new(10.0, 20.0, 30.0));
+
+        // Two bytes per sample, most significant first
+        let mut data = b"P6 1 1 65535\n".to_vec();
+        data.extend_from_slice(&[255, 255, 0, 0, 128, 0]);
+        let texture = ImageTexture::from_ppm(&data, ColorSpace::Linear).unwrap();
+        assert_eq!(texture.pixel(0, 0).x, 255.0);
+        assert_eq!(texture.pixel(0, 0).y, 0.0);
+        assert!((texture.pixel(0, 0).z - 127.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn png() {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, 2, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&[255, 0, 0, 255, 0, 64, 128, 0])
+            .unwrap();
+        writer.finish().unwrap();
+
+        let texture = ImageTexture::from_png(&data, ColorSpace::Linear).unwrap();
+        assert_eq!((texture.width(), texture.height()), (2, 1));
+        assert_eq!(texture.pixel(0, 0), Vec3::new(255.0, 0.0, 0.0));
+        assert_eq!(texture.pixel(1, 0), Vec3::new(0.0, 64.0, 128.0));
+        assert!(ImageTexture::from_png(b"\x89PNG garbage", ColorSpace::Linear).is_err());
+    }
+
+    #[test]
+    fn srgb_is_decoded() {
+        let data = b"P3 3 1 255 0 0 0  128 128 128  255 255 255\n";
+        let color = ImageTexture::from_ppm(data, ColorSpace::Srgb).unwrap();
+        assert_eq!(color.pixel(0, 0), Vec3::null());
+        assert!((color.pixel(1, 0).x / 255.0 - 0.2158605).abs() < 1e-6);
+        assert_eq!(color.pixel(2, 0), Vec3::new(255.0, 255.0, 255.0));
+        // Data maps keep their samples
+        let data_map = ImageTexture::from_ppm(data, ColorSpace::Linear).unwrap();
+        assert_eq!(data_map.pixel(1, 0), Vec3::new(128.0, 128.0, 128.0));
+
+        // Mipmaps average the decoded light
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, 2, 1);
+        encoder.set_color(png::ColorType::Grayscale);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0, 128]).unwrap();
+        writer.finish().unwrap();
+        let texture = ImageTexture::from_png(&png, ColorSpace::Srgb).unwrap();
+        assert_eq!(texture.lookup((0.5, 0.5), 1), color.pixel(1, 0) / 2.0);
+    }
+}
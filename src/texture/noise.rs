@@ -0,0 +1,276 @@
+//! Solid textures built from Perlin noise, evaluated at the position of the hit point.
+//! https://mrl.cs.nyu.edu/~perlin/noise/
+
+use contracts::*;
+
+use crate::texture::Texture;
+use crate::vec3::{Pnt3, Vec3};
+
+const TABLE_SIZE: usize = 256;
+
+/// Gradient noise on the integer lattice, from -1.0 to 1.0 and 0.0 at lattice points.
+/// The lattice is shuffled by a random source, the same seed gives the same noise.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Perlin {
+    /// A permutation of `0..TABLE_SIZE`, repeated once to skip wrapping indices.
+    permutation: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(rand: &mut dyn random::Source) -> Perlin {
+        let mut permutation: Vec<usize> = (0..TABLE_SIZE).collect();
+        // Fisher–Yates shuffle
+        for i in (1..TABLE_SIZE).rev() {
+            let j = (rand.read_u64() % (i as u64 + 1)) as usize;
+            permutation.swap(i, j);
+        }
+        permutation.extend_from_within(..);
+        Perlin { permutation }
+    }
+
+    /// Improved Perlin noise at `p`.
+    pub fn noise(&self, p: Pnt3) -> f64 {
+        let cell = |x: f64| (x.floor() as i64).rem_euclid(TABLE_SIZE as i64) as usize;
+        let (xi, yi, zi) = (cell(p.x), cell(p.y), cell(p.z));
+        let (x, y, z) = (p.x - p.x.floor(), p.y - p.y.floor(), p.z - p.z.floor());
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let perm = &self.permutation;
+        let hash = |dx: usize, dy: usize, dz: usize| perm[perm[perm[xi + dx] + yi + dy] + zi + dz];
+        let corner = |dx: usize, dy: usize, dz: usize| {
+            gradient(
+                hash(dx, dy, dz),
+                x - dx as f64,
+                y - dy as f64,
+                z - dz as f64,
+            )
+        };
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+            ),
+        )
+    }
+
+    /// Fractal Brownian motion, `octaves` layers of noise each at double the frequency
+    /// and half the amplitude of the previous one. Normalized to -1.0 to 1.0.
+    #[requires(octaves > 0)]
+    pub fn fbm(&self, p: Pnt3, octaves: u32) -> f64 {
+        self.octaves(p, octaves, |n| n)
+    }
+
+    /// Like [`Perlin::fbm`] but summing the absolute noise, from 0.0 to 1.0.
+    /// The creases where the noise changes sign give a billowy look.
+    #[requires(octaves > 0)]
+    pub fn turbulence(&self, p: Pnt3, octaves: u32) -> f64 {
+        self.octaves(p, octaves, f64::abs)
+    }
+
+    fn octaves(&self, p: Pnt3, octaves: u32, f: impl Fn(f64) -> f64) -> f64 {
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut p = p;
+        for _ in 0..octaves {
+            sum += amplitude * f(self.noise(p));
+            total += amplitude;
+            amplitude *= 0.5;
+            p = p * 2.0;
+        }
+        sum / total
+    }
+}
+
+/// Smooth interpolation weight with vanishing first and second derivatives at 0 and 1.
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// Dot product of the offset with one of the 12 cube edge directions picked by `hash`.
+fn gradient(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// How the noise is shaped into a pattern.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Pattern {
+    /// Smooth clouds of fractal noise.
+    #[default]
+    Fbm,
+    /// Billowy fractal noise of absolute values.
+    Turbulence,
+    /// Veins along the x axis, a sine wave distorted by turbulence.
+    Marble,
+    /// Rings around the y axis, distorted by turbulence.
+    Wood,
+}
+
+/// A solid texture blending between two values by a noise pattern.
+#[derive(Debug, PartialEq, Clone)]
+pub struct NoiseTexture {
+    pub perlin: Perlin,
+    pub pattern: Pattern,
+    /// Lattice cells per unit of distance.
+    pub frequency: f64,
+    pub octaves: u32,
+    /// Value where the pattern is 0.0.
+    pub low: Vec3,
+    /// Value where the pattern is 1.0.
+    pub high: Vec3,
+}
+
+impl NoiseTexture {
+    pub fn new(rand: &mut dyn random::Source, pattern: Pattern) -> NoiseTexture {
+        NoiseTexture {
+            perlin: Perlin::new(rand),
+            pattern,
+            frequency: 1.0,
+            octaves: 6,
+            low: Vec3::null(),
+            high: Vec3::new(255.0, 255.0, 255.0),
+        }
+    }
+
+    /// Returns the texture with `frequency` lattice cells per unit of distance.
+    #[requires(frequency > 0.0)]
+    pub fn with_frequency(mut self, frequency: f64) -> NoiseTexture {
+        self.frequency = frequency;
+        self
+    }
+
+    /// Returns the texture summing `octaves` layers of noise.
+    #[requires(octaves > 0)]
+    pub fn with_octaves(mut self, octaves: u32) -> NoiseTexture {
+        self.octaves = octaves;
+        self
+    }
+
+    /// Returns the texture blending from `low` to `high`.
+    pub fn with_values(mut self, low: Vec3, high: Vec3) -> NoiseTexture {
+        self.low = low;
+        self.high = high;
+        self
+    }
+
+    /// The pattern at `p`, from 0.0 to 1.0.
+    pub fn pattern_at(&self, p: Pnt3) -> f64 {
+        let p = p * self.frequency;
+        let t = match self.pattern {
+            Pattern::Fbm => 0.5 + 0.5 * self.perlin.fbm(p, self.octaves),
+            Pattern::Turbulence => self.perlin.turbulence(p, self.octaves),
+            Pattern::Marble => {
+                let phase = p.x + 10.0 * self.perlin.turbulence(p, self.octaves);
+                0.5 + 0.5 * phase.sin()
+            }
+            Pattern::Wood => {
+                let radius = (p.x * p.x + p.z * p.z).sqrt();
+                let rings = radius + 0.5 * self.perlin.turbulence(p, self.octaves);
+                rings - rings.floor()
+            }
+        };
+        t.clamp(0.0, 1.0)
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _uv: (f64, f64), p: Pnt3, _footprint: f64) -> Vec3 {
+        let t = self.pattern_at(p);
+        self.low * (1.0 - t) + self.high * t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> impl Iterator<Item = Pnt3> {
+        let mut rand = random::default(42);
+        (0..1000).map(move |_| {
+            let mut read = || (random::Source::read_f64(&mut rand) - 0.5) * 20.0;
+            Pnt3::new(read(), read(), read())
+        })
+    }
+
+    #[test]
+    fn noise_is_bounded_and_zero_on_the_lattice() {
+        let perlin = Perlin::new(&mut random::default(42));
+        assert_eq!(perlin.noise(Pnt3::new(3.0, -2.0, 7.0)), 0.0);
+        let mut spread: f64 = 0.0;
+        for p in points() {
+            let n = perlin.noise(p);
+            assert!((-1.0..=1.0).contains(&n));
+            spread = spread.max(n.abs());
+            assert!((0.0..=1.0).contains(&perlin.turbulence(p, 4)));
+            assert!((-1.0..=1.0).contains(&perlin.fbm(p, 4)));
+        }
+        assert!(spread > 0.3);
+    }
+
+    #[test]
+    fn noise_is_smooth() {
+        let perlin = Perlin::new(&mut random::default(42));
+        for p in points() {
+            let step = perlin.noise(p + Vec3::new(1e-4, 0.0, 0.0)) - perlin.noise(p);
+            assert!(step.abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn seeds_are_deterministic() {
+        let p = Pnt3::new(0.3, 1.7, -2.2);
+        let a = Perlin::new(&mut random::default(42));
+        let b = Perlin::new(&mut random::default(42));
+        let c = Perlin::new(&mut random::default(7));
+        assert_eq!(a, b);
+        assert_eq!(a.noise(p), b.noise(p));
+        assert_ne!(a.noise(p), c.noise(p));
+    }
+
+    #[test]
+    fn patterns_blend_between_the_values() {
+        let low = Vec3::new(10.0, 20.0, 30.0);
+        let high = Vec3::new(200.0, 100.0, 50.0);
+        for pattern in [
+            Pattern::Fbm,
+            Pattern::Turbulence,
+            Pattern::Marble,
+            Pattern::Wood,
+        ] {
+            let texture = NoiseTexture::new(&mut random::default(42), pattern)
+                .with_frequency(0.5)
+                .with_octaves(4)
+                .with_values(low, high);
+            let mut seen = (1.0f64, 0.0f64);
+            for p in points() {
+                let t = texture.pattern_at(p);
+                assert!((0.0..=1.0).contains(&t));
+                seen = (seen.0.min(t), seen.1.max(t));
+                let value = texture.value((0.0, 0.0), p, 0.0);
+                assert!((value - (low * (1.0 - t) + high * t)).len() < 1e-9);
+            }
+            // Every pattern varies noticeably
+            assert!(seen.1 - seen.0 > 0.3, "{:?}", pattern);
+        }
+    }
+}
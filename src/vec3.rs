@@ -87,6 +87,10 @@ impl Vec3 {
             z: 0.0,
         }
     }
+
+    pub fn max_component(&self) -> f64 {
+        self.x.max(self.y).max(self.z)
+    }
 }
 
 pub struct RandomInUnitSphere<'a> {
@@ -135,7 +139,7 @@ impl UnitVec3 {
             rand.read_f64() * 2.0 - 1.0,
             rand.read_f64() * 2.0 - 1.0,
         );
-        while vec.len().abs() < std::f64::EPSILON {
+        while vec.len().abs() < f64::EPSILON {
             vec = Vec3::new(
                 rand.read_f64() * 2.0 - 1.0,
                 rand.read_f64() * 2.0 - 1.0,
@@ -255,6 +259,37 @@ impl std::ops::Mul<f64> for Vec3 {
     }
 }
 
+/// Component-wise product, e.g. for filtering a color by a reflectance.
+impl std::ops::Mul<Vec3> for Vec3 {
+    type Output = Self;
+
+    #[ensures(ret.x == self.x * other.x)]
+    #[ensures(ret.y == self.y * other.y)]
+    #[ensures(ret.z == self.z * other.z)]
+    fn mul(self, other: Vec3) -> Self {
+        Vec3 {
+            x: self.x * other.x,
+            y: self.y * other.y,
+            z: self.z * other.z,
+        }
+    }
+}
+
+impl std::ops::Div<f64> for Vec3 {
+    type Output = Self;
+
+    #[ensures(ret.x == self.x / other)]
+    #[ensures(ret.y == self.y / other)]
+    #[ensures(ret.z == self.z / other)]
+    fn div(self, other: f64) -> Self {
+        Vec3 {
+            x: self.x / other,
+            y: self.y / other,
+            z: self.z / other,
+        }
+    }
+}
+
 impl std::fmt::Display for Vec3 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "({} {} {})", self.x, self.y, self.z)
@@ -405,6 +440,15 @@ mod tests {
         assert_eq!(c.z, 6.0);
     }
 
+    #[test]
+    fn vec3_mul_vec3() {
+        let a = Vec3::new(1.0, 2.0, 3.0);
+        let b = Vec3::new(0.5, 0.0, -1.0);
+        assert_eq!(a * b, Vec3::new(0.5, 0.0, -3.0));
+        assert_eq!(a / 2.0, Vec3::new(0.5, 1.0, 1.5));
+        assert_eq!(b.max_component(), 0.5);
+    }
+
     #[test]
     fn vec3_dot() {
         let a = Vec3 {